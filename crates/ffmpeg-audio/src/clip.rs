@@ -0,0 +1,92 @@
+use crate::copy::PacketCopier;
+use crate::probe::probe;
+use crate::transcode::{SpeechCodec, Transcoder};
+use crate::ChunkInfo;
+use ffmpeg_next as ffmpeg;
+use std::path::Path;
+
+// clips are for listening, keep more than the speech upload does
+const CLIP_SAMPLE_RATE: u32 = 48000;
+const CLIP_BITRATE: usize = 128_000;
+
+// cut `[start, end)` seconds out of `input_file` into `output_file`, copying the
+// packets when the output container can hold the input codec and re-encoding to
+// the codec matching the output extension otherwise
+pub fn extract_clip(
+    input_file: &Path,
+    output_file: &Path,
+    start: f64,
+    end: f64,
+) -> Result<ChunkInfo, ffmpeg::Error> {
+    if end <= start || start.is_nan() || end.is_nan() {
+        return Err(ffmpeg::Error::InvalidData);
+    }
+    if let Some(parent) = output_file.parent() {
+        std::fs::create_dir_all(parent).map_err(|_| ffmpeg::Error::Other { errno: -1 })?;
+    }
+
+    let mut copier = PacketCopier::open(input_file)?;
+    let offset = copier.stream_start();
+    let copied = copier.copy(output_file, start + offset, end + offset, start > 0.0, None);
+    let copy_error = match copied {
+        Ok(copied) => {
+            let first_time = copied.first_time.unwrap_or(copied.last_end);
+            return Ok(ChunkInfo {
+                path: output_file.to_path_buf(),
+                index: 0,
+                start: (first_time - offset).max(0.0),
+                duration: (copied.last_end - first_time).max(0.0),
+            });
+        }
+        Err(e) => e,
+    };
+    // the muxer refused the input codec, e.g. opus into an mp3 file
+    let _ = std::fs::remove_file(output_file);
+
+    let codec = output_file
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(SpeechCodec::from_name)
+        .ok_or(copy_error)?;
+    Transcoder::new(codec)
+        .with_sample_rate(CLIP_SAMPLE_RATE)
+        .with_bitrate(CLIP_BITRATE)
+        .with_filter(format!(
+            "atrim=start={:.3}:end={:.3},asetpts=PTS-STARTPTS",
+            start + offset,
+            end + offset
+        ))
+        .transcode(input_file, output_file)?;
+
+    Ok(ChunkInfo {
+        path: output_file.to_path_buf(),
+        index: 0,
+        start,
+        duration: probe(output_file)?.duration,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn extract_clip_copies_packets() {
+        let input_file = Path::new("./sample.m4a");
+        let output_file = Path::new("output_dir/clip.m4a");
+        let clip = extract_clip(input_file, output_file, 30.0, 45.0).unwrap();
+        assert!((clip.start - 30.0).abs() < 0.1);
+        assert!((clip.duration - 15.0).abs() < 0.1);
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn extract_clip_reencodes_for_other_containers() {
+        let input_file = Path::new("./sample.m4a");
+        let output_file = Path::new("output_dir/clip.flac");
+        let clip = extract_clip(input_file, output_file, 30.0, 45.0).unwrap();
+        assert!((clip.duration - 15.0).abs() < 0.1);
+        assert!(extract_clip(input_file, output_file, 45.0, 30.0).is_err());
+    }
+}
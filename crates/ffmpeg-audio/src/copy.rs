@@ -0,0 +1,169 @@
+use ffmpeg_next::{self as ffmpeg, codec, format, Rational};
+use std::path::Path;
+
+use crate::{CancelToken, CONTAINER_OVERHEAD, PACKET_OVERHEAD};
+
+// stop an output before it grows past `max_bytes`, but only once it reaches
+// `after` seconds so every output makes progress
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SizeLimit {
+    pub max_bytes: u64,
+    pub after: f64,
+}
+
+// what one `copy` call wrote, times are seconds on the input stream's timeline
+pub(crate) struct CopiedRange {
+    pub first_time: Option<f64>,
+    pub last_end: f64,
+    // set when the size limit ended the output early, the time of the first packet left out
+    pub rolled_at: Option<f64>,
+}
+
+// remuxes ranges of the best audio stream into new files without decoding
+pub(crate) struct PacketCopier {
+    input_ctx: format::context::Input,
+    stream_index: usize,
+    parameters: codec::Parameters,
+    time_base: Rational,
+    stream_start: f64,
+    cancel: CancelToken,
+}
+
+impl PacketCopier {
+    pub fn open(input_file: &Path) -> Result<Self, ffmpeg::Error> {
+        ffmpeg::init()?;
+        let input_ctx = ffmpeg::format::input(input_file)?;
+        let audio_stream = input_ctx
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+
+        let stream_index = audio_stream.index();
+        let parameters = audio_stream.parameters();
+        let time_base = audio_stream.time_base();
+        let stream_start = match audio_stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0.0,
+            start_time => start_time as f64 * f64::from(time_base),
+        };
+
+        Ok(Self {
+            input_ctx,
+            stream_index,
+            parameters,
+            time_base,
+            stream_start,
+            cancel: CancelToken::new(),
+        })
+    }
+
+    // stop a running `copy` at the next packet once `cancel` is set
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // where the stream starts, subtract it from copied times to get file relative ones
+    pub fn stream_start(&self) -> f64 {
+        self.stream_start
+    }
+
+    // of the whole input in seconds, from the container header
+    pub fn duration(&self) -> f64 {
+        self.input_ctx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE)
+    }
+
+    // copy the packets in `[start_time, end_time)` into `output_path`, seeking first
+    // when `seek` is set, otherwise reading on from the current position
+    pub fn copy(
+        &mut self,
+        output_path: &Path,
+        start_time: f64,
+        end_time: f64,
+        seek: bool,
+        limit: Option<SizeLimit>,
+    ) -> Result<CopiedRange, ffmpeg::Error> {
+        let codec = codec::context::Context::from_parameters(self.parameters.clone())?;
+        let mut output_ctx = ffmpeg::format::output(&output_path)?;
+
+        let mut output_stream = output_ctx.add_stream(codec.codec())?;
+        output_stream.set_parameters(self.parameters.clone());
+        output_stream.set_time_base(self.time_base);
+
+        if seek {
+            let start_ts = (start_time.max(0.0) * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+            self.input_ctx.seek(start_ts, ..start_ts)?;
+        }
+
+        output_ctx.set_metadata(self.input_ctx.metadata().to_owned());
+        output_ctx.write_header()?;
+
+        let mut last_pts = 0;
+        let mut pts_offset = 0;
+        let mut first_pts = None;
+        let mut first_time = None;
+        let mut last_end = 0.0;
+        let mut rolled_at = None;
+        let mut written = CONTAINER_OVERHEAD;
+        for (stream, packet) in self.input_ctx.packets() {
+            if self.cancel.is_cancelled() {
+                return Err(ffmpeg::Error::Exit);
+            }
+            if stream.index() != self.stream_index {
+                continue;
+            }
+            let pts = packet.pts().unwrap_or(0);
+            let current_time = pts as f64 * f64::from(stream.time_base());
+
+            if current_time >= end_time {
+                break;
+            }
+            // the seek lands on the packet before the start, which belongs to the previous chunk
+            if current_time < start_time {
+                continue;
+            }
+            if let Some(limit) = limit {
+                let size = packet.size() as u64 + PACKET_OVERHEAD;
+                if written + size > limit.max_bytes
+                    && first_time.is_some()
+                    && current_time > limit.after
+                {
+                    rolled_at = Some(current_time);
+                    break;
+                }
+                written += size;
+            }
+            first_time.get_or_insert(current_time);
+            last_end = (pts + packet.duration()) as f64 * f64::from(stream.time_base());
+            let mut new_packet = packet.clone();
+            new_packet.set_position(-1);
+            new_packet.set_stream(0);
+
+            if let Some(first_pts_value) = first_pts {
+                let adjusted_pts = pts - first_pts_value;
+
+                if adjusted_pts < last_pts {
+                    pts_offset = last_pts;
+                }
+
+                let final_pts = adjusted_pts + pts_offset;
+                new_packet.set_pts(Some(final_pts));
+                new_packet.set_dts(Some(final_pts));
+
+                last_pts = final_pts;
+            } else {
+                first_pts = Some(pts);
+                new_packet.set_pts(Some(0));
+                new_packet.set_dts(Some(0));
+            }
+
+            new_packet.write_interleaved(&mut output_ctx)?;
+        }
+        output_ctx.write_trailer()?;
+
+        Ok(CopiedRange {
+            first_time,
+            last_end,
+            rolled_at,
+        })
+    }
+}
@@ -0,0 +1,188 @@
+use ffmpeg_next::{self as ffmpeg, codec, filter, format, frame, ChannelLayout, Rational};
+use std::path::Path;
+
+// build an `abuffer -> spec -> abuffersink` graph fed by frames from `decoder`
+pub(crate) fn build_graph(
+    decoder: &codec::decoder::Audio,
+    time_base: Rational,
+    spec: &str,
+) -> Result<filter::Graph, ffmpeg::Error> {
+    let mut graph = filter::Graph::new();
+
+    let channel_layout = if decoder.channel_layout().bits() == 0 {
+        ChannelLayout::default(decoder.channels() as i32)
+    } else {
+        decoder.channel_layout()
+    };
+    let args = format!(
+        "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        time_base,
+        decoder.rate(),
+        decoder.format().name(),
+        channel_layout.bits()
+    );
+
+    let abuffer = filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+    let abuffersink = filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+    graph.add(&abuffer, "in", &args)?;
+    graph.add(&abuffersink, "out", "")?;
+
+    graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+// decodes the best audio stream to mono f32 samples at a fixed rate
+pub(crate) struct MonoDecoder {
+    input_ctx: format::context::Input,
+    stream_index: usize,
+    time_base: Rational,
+    decoder: codec::decoder::Audio,
+    sample_rate: u32,
+}
+
+impl MonoDecoder {
+    pub fn open(input_file: &Path, sample_rate: u32) -> Result<Self, ffmpeg::Error> {
+        ffmpeg::init()?;
+        let input_ctx = ffmpeg::format::input(input_file)?;
+        let stream = input_ctx
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+        let decoder = codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .audio()?;
+
+        Ok(Self {
+            input_ctx,
+            stream_index,
+            time_base,
+            decoder,
+            sample_rate,
+        })
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.input_ctx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE)
+    }
+
+    // feed the samples in `[start, end)` to `on_samples` together with the time of
+    // the first sample of each batch, stop early when it returns false
+    pub fn read<F>(&mut self, start: f64, end: f64, mut on_samples: F) -> Result<(), ffmpeg::Error>
+    where
+        F: FnMut(f64, &[f32]) -> bool,
+    {
+        if start > 0.0 {
+            let ts = (start * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+            self.input_ctx.seek(ts, ..ts)?;
+        }
+        self.decoder.flush();
+
+        let spec = format!(
+            "aformat=sample_fmts=flt:channel_layouts=mono:sample_rates={}",
+            self.sample_rate
+        );
+        let mut graph = build_graph(&self.decoder, self.time_base, &spec)?;
+        let mut window = Window {
+            sink_time_base: graph.get("out").unwrap().sink().time_base(),
+            sample_rate: self.sample_rate,
+            start,
+            end,
+            next_time: None,
+        };
+
+        let mut decoded = frame::Audio::empty();
+        let mut packets = self.input_ctx.packets();
+        let mut eof = false;
+        while !eof {
+            match packets.next() {
+                Some((stream, packet)) => {
+                    if stream.index() != self.stream_index
+                        || self.decoder.send_packet(&packet).is_err()
+                    {
+                        continue;
+                    }
+                }
+                None => {
+                    self.decoder.send_eof()?;
+                    eof = true;
+                }
+            }
+
+            while self.decoder.receive_frame(&mut decoded).is_ok() {
+                let timestamp = decoded.timestamp();
+                decoded.set_pts(timestamp);
+                graph.get("in").unwrap().source().add(&decoded)?;
+                if window.drain(&mut graph, &mut on_samples) {
+                    return Ok(());
+                }
+            }
+        }
+
+        graph.get("in").unwrap().source().flush()?;
+        window.drain(&mut graph, &mut on_samples);
+        Ok(())
+    }
+
+    // collect the samples in `[start, end)`, returns the time of the first one
+    pub fn read_range(&mut self, start: f64, end: f64) -> Result<(f64, Vec<f32>), ffmpeg::Error> {
+        let mut first_time = None;
+        let mut samples = Vec::new();
+        self.read(start, end, |time, batch| {
+            first_time.get_or_insert(time);
+            samples.extend_from_slice(batch);
+            true
+        })?;
+        Ok((first_time.unwrap_or(start), samples))
+    }
+}
+
+struct Window {
+    sink_time_base: Rational,
+    sample_rate: u32,
+    start: f64,
+    end: f64,
+    next_time: Option<f64>,
+}
+
+impl Window {
+    // returns true once the end of the window is reached or the consumer stops
+    fn drain<F>(&mut self, graph: &mut filter::Graph, on_samples: &mut F) -> bool
+    where
+        F: FnMut(f64, &[f32]) -> bool,
+    {
+        let rate = self.sample_rate as f64;
+        let mut filtered = frame::Audio::empty();
+        while graph
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            let frame_time = match (self.next_time, filtered.pts()) {
+                (Some(time), _) => time,
+                (None, Some(pts)) => pts as f64 * f64::from(self.sink_time_base),
+                (None, None) => self.start,
+            };
+            let samples = filtered.plane::<f32>(0);
+            self.next_time = Some(frame_time + samples.len() as f64 / rate);
+
+            let skip = ((self.start - frame_time) * rate).ceil().max(0.0) as usize;
+            if skip >= samples.len() {
+                continue;
+            }
+            let batch_time = frame_time + skip as f64 / rate;
+            if batch_time >= self.end {
+                return true;
+            }
+            let take = (((self.end - batch_time) * rate).ceil() as usize).min(samples.len() - skip);
+            if !on_samples(batch_time, &samples[skip..skip + take]) || skip + take < samples.len() {
+                return true;
+            }
+        }
+        false
+    }
+}
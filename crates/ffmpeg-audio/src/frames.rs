@@ -0,0 +1,241 @@
+#[cfg(feature = "ffmpeg")]
+use ffmpeg_next::{self as ffmpeg, codec, format, frame, media, software::scaling, Discard};
+#[cfg(feature = "ffmpeg")]
+use std::path::Path;
+use std::path::PathBuf;
+
+// lambda per quantizer step, FF_QP2LAMBDA in libavutil
+#[cfg(feature = "ffmpeg")]
+const QP2LAMBDA: i32 = 118;
+// jpeg quantizer, 2 is near lossless and 31 the worst
+#[cfg(feature = "ffmpeg")]
+const JPEG_QUALITY: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    // needs an ffmpeg built with libwebp
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+
+    #[cfg(feature = "ffmpeg")]
+    fn encoder(&self) -> Option<ffmpeg::Codec> {
+        match self {
+            Self::Jpeg => ffmpeg::encoder::find(codec::Id::MJPEG),
+            Self::Webp => ffmpeg::encoder::find_by_name("libwebp"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThumbnailConfig {
+    pub format: ImageFormat,
+    // wider frames are scaled down keeping their aspect, 0 keeps the full size
+    pub max_width: u32,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            format: ImageFormat::Jpeg,
+            max_width: 640,
+        }
+    }
+}
+
+// one still written by `extract_keyframes`
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub path: PathBuf,
+    // position in the requested times
+    pub index: usize,
+    // seconds, where the keyframe actually sits, at or before the requested time
+    pub time: f64,
+    pub width: u32,
+    pub height: u32,
+}
+
+// scale `width` x `height` down to `max_width`, even sizes as 4:2:0 needs
+#[cfg(feature = "ffmpeg")]
+fn fit_width(width: u32, height: u32, max_width: u32) -> (u32, u32) {
+    let (width, height) = if max_width > 0 && width > max_width {
+        let scaled = (height as u64 * max_width as u64 / width as u64) as u32;
+        (max_width, scaled)
+    } else {
+        (width, height)
+    };
+    ((width & !1).max(2), (height & !1).max(2))
+}
+
+// the first frame decoded from the current position, with non key frames
+// skipped that is the keyframe a seek landed on
+#[cfg(feature = "ffmpeg")]
+fn next_keyframe(
+    input_ctx: &mut format::context::Input,
+    decoder: &mut codec::decoder::Video,
+    stream_index: usize,
+) -> Result<Option<frame::Video>, ffmpeg::Error> {
+    let mut decoded = frame::Video::empty();
+    for (stream, packet) in input_ctx.packets() {
+        if stream.index() != stream_index || decoder.send_packet(&packet).is_err() {
+            continue;
+        }
+        if decoder.receive_frame(&mut decoded).is_ok() {
+            return Ok(Some(decoded));
+        }
+    }
+    decoder.send_eof()?;
+    if decoder.receive_frame(&mut decoded).is_ok() {
+        return Ok(Some(decoded));
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "ffmpeg")]
+fn write_image(
+    decoded: &frame::Video,
+    output_file: &Path,
+    config: &ThumbnailConfig,
+) -> Result<(u32, u32), ffmpeg::Error> {
+    let image_codec = config
+        .format
+        .encoder()
+        .ok_or(ffmpeg::Error::EncoderNotFound)?
+        .video()?;
+    let pixel_format = image_codec
+        .formats()
+        .and_then(|mut formats| formats.next())
+        .ok_or(ffmpeg::Error::InvalidData)?;
+    let (width, height) = fit_width(decoded.width(), decoded.height(), config.max_width);
+
+    let mut scaler = scaling::Context::get(
+        decoded.format(),
+        decoded.width(),
+        decoded.height(),
+        pixel_format,
+        width,
+        height,
+        scaling::Flags::BILINEAR,
+    )?;
+    let mut scaled = frame::Video::empty();
+    scaler.run(decoded, &mut scaled)?;
+    scaled.set_pts(Some(0));
+
+    // the image2 muxer for jpg, the webp one for webp
+    let mut output_ctx = format::output(output_file)?;
+    let mut output_stream = output_ctx.add_stream(image_codec)?;
+    let mut encoder = codec::context::Context::from_parameters(output_stream.parameters())?
+        .encoder()
+        .video()?;
+    encoder.set_width(width);
+    encoder.set_height(height);
+    encoder.set_format(pixel_format);
+    encoder.set_time_base((1, 1));
+    if config.format == ImageFormat::Jpeg {
+        encoder.set_flags(codec::flag::Flags::QSCALE);
+        encoder.set_global_quality(JPEG_QUALITY * QP2LAMBDA);
+    }
+    output_stream.set_time_base((1, 1));
+
+    let mut encoder = encoder.open_as(image_codec)?;
+    output_stream.set_parameters(&encoder);
+    output_ctx.write_header()?;
+
+    encoder.send_frame(&scaled)?;
+    encoder.send_eof()?;
+    let mut encoded = ffmpeg::Packet::empty();
+    while encoder.receive_packet(&mut encoded).is_ok() {
+        encoded.set_stream(0);
+        encoded.write_interleaved(&mut output_ctx)?;
+    }
+    output_ctx.write_trailer()?;
+    Ok((width, height))
+}
+
+// write the keyframe at or before each of `times` (seconds) into `frame_001.jpg`,
+// `frame_002.jpg` ... in `output_dir`, times with no keyframe left before the end are skipped
+#[cfg(feature = "ffmpeg")]
+pub fn extract_keyframes(
+    input_file: &Path,
+    times: &[f64],
+    output_dir: &Path,
+    config: &ThumbnailConfig,
+) -> Result<Vec<Thumbnail>, ffmpeg::Error> {
+    ffmpeg::init()?;
+    std::fs::create_dir_all(output_dir).map_err(|_| ffmpeg::Error::Other { errno: -1 })?;
+
+    let mut input_ctx = format::input(input_file)?;
+    let stream = input_ctx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+    let mut decoder = codec::context::Context::from_parameters(stream.parameters())?.decoder();
+    // only keyframes are ever kept, do not spend time on the rest
+    decoder.skip_frame(Discard::NonKey);
+    let mut decoder = decoder.video()?;
+
+    let mut thumbnails = Vec::new();
+    for (index, &time) in times.iter().enumerate() {
+        let ts = (time.max(0.0) * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        input_ctx.seek(ts, ..ts)?;
+        decoder.flush();
+
+        let Some(decoded) = next_keyframe(&mut input_ctx, &mut decoder, stream_index)? else {
+            continue;
+        };
+        let path = output_dir.join(format!(
+            "frame_{:03}.{}",
+            index + 1,
+            config.format.extension()
+        ));
+        let (width, height) = write_image(&decoded, &path, config)?;
+        thumbnails.push(Thumbnail {
+            path,
+            index,
+            time: decoded
+                .timestamp()
+                .map_or(time, |pts| pts as f64 * time_base),
+            width,
+            height,
+        });
+    }
+    Ok(thumbnails)
+}
+
+#[cfg(all(test, feature = "ffmpeg"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_width_works() {
+        assert_eq!(fit_width(1920, 1080, 640), (640, 360));
+        assert_eq!(fit_width(320, 241, 640), (320, 240));
+        assert_eq!(fit_width(1280, 720, 0), (1280, 720));
+        assert_eq!(fit_width(1000, 3, 500), (500, 2));
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.mp4, a local recording that is not committed"]
+    fn extract_keyframes_works() {
+        let input_file = Path::new("./sample.mp4");
+        let output_dir = Path::new("output_dir/frames");
+        let config = ThumbnailConfig::default();
+        let thumbnails = extract_keyframes(input_file, &[0.0, 30.0], output_dir, &config).unwrap();
+        assert_eq!(thumbnails.len(), 2);
+        for thumbnail in &thumbnails {
+            assert!(thumbnail.path.exists());
+            assert!(thumbnail.width <= config.max_width);
+        }
+        assert!(thumbnails[1].time <= 30.0);
+    }
+}
@@ -1,105 +1,283 @@
+#[cfg(feature = "ffmpeg")]
 use ffmpeg_next::{self as ffmpeg};
-use hound::{WavReader, WavWriter};
+use hound::{Sample, SampleFormat, WavReader, WavWriter};
 use std::error::Error;
-use std::fs;
-use std::path::Path;
-
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "ffmpeg")]
+mod clip;
+#[cfg(feature = "ffmpeg")]
+mod copy;
+#[cfg(feature = "ffmpeg")]
+mod decode;
+mod frames;
+#[cfg(feature = "pure-rust")]
+pub mod native;
+mod peaks;
+mod preprocess;
+mod probe;
+mod progress;
+mod silence;
+mod subtitles;
+mod transcode;
+mod vad;
+
+#[cfg(feature = "ffmpeg")]
+pub use clip::extract_clip;
+#[cfg(feature = "ffmpeg")]
+use copy::{PacketCopier, SizeLimit};
+#[cfg(feature = "ffmpeg")]
+use decode::MonoDecoder;
+#[cfg(feature = "ffmpeg")]
+pub use frames::extract_keyframes;
+pub use frames::{ImageFormat, Thumbnail, ThumbnailConfig};
+#[cfg(all(feature = "pure-rust", not(feature = "ffmpeg")))]
+use native::MonoDecoder;
+pub use peaks::{generate_peaks, PeaksConfig, Waveform};
+pub use preprocess::Preprocess;
+#[cfg(feature = "ffmpeg")]
+pub use probe::probe;
+pub use probe::{MediaInfo, StreamInfo, StreamKind};
+pub use progress::CancelToken;
+#[cfg(feature = "ffmpeg")]
+use progress::Progress;
+pub use silence::SilenceConfig;
+#[cfg(feature = "ffmpeg")]
+pub use subtitles::{extract_subtitles, list_subtitle_tracks};
+pub use subtitles::{SubtitleCue, SubtitleTrack};
+pub use transcode::{
+    is_missing_encoder, mime_type, tempo_filter, SpeechCodec, Transcoder, SPEECH_SAMPLE_RATE,
+};
+pub use vad::{detect_speech, speech_filter, SpeechRegion, TimeMap, VadConfig};
+
+#[cfg(not(any(feature = "ffmpeg", feature = "pure-rust")))]
+compile_error!("enable the `ffmpeg` or the `pure-rust` feature");
+
+// without libav probing, splitting, clips and decoding come from the pure Rust backend
+#[cfg(all(feature = "pure-rust", not(feature = "ffmpeg")))]
+pub use native::{
+    extract_clip, extract_keyframes, extract_subtitles, list_subtitle_tracks, probe, AudioSplitter,
+};
+
+// room kept free in size bounded outputs of either backend for the container
+// header and trailer, plus a per packet allowance for framing and index entries
+pub(crate) const CONTAINER_OVERHEAD: u64 = 64 * 1024;
+pub(crate) const PACKET_OVERHEAD: u64 = 32;
+
+// the error of the functions both backends provide
+#[cfg(feature = "ffmpeg")]
+pub type BackendError = ffmpeg::Error;
+#[cfg(not(feature = "ffmpeg"))]
+pub type BackendError = Box<dyn Error>;
+
+#[cfg(feature = "ffmpeg")]
+#[derive(Debug, Clone)]
 pub struct AudioSplitter {
     chunk_duration: i64,
+    silence: Option<SilenceConfig>,
+    overlap: f64,
+    max_bytes: Option<u64>,
 }
 
 pub struct WavSplitter {
     chunk_duration: u32,
 }
 
+// one output file of a split, times are seconds on the timeline of the input
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+    pub path: PathBuf,
+    pub index: usize,
+    pub start: f64,
+    pub duration: f64,
+}
+
+impl ChunkInfo {
+    // the whole input as a single chunk, for files small enough to skip splitting
+    pub fn whole(path: PathBuf, duration: f64) -> Self {
+        Self {
+            path,
+            index: 0,
+            start: 0.0,
+            duration,
+        }
+    }
+
+    pub fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+}
+
+#[cfg(feature = "ffmpeg")]
 impl AudioSplitter {
     pub fn new(duration_seconds: i64) -> Self {
         Self {
             chunk_duration: duration_seconds,
+            silence: None,
+            overlap: 0.0,
+            max_bytes: None,
         }
     }
 
-    pub fn split(&self, input_file: &Path, output_dir: &Path) -> Result<(), ffmpeg::Error> {
-        ffmpeg::init()?;
-        if !output_dir.is_dir() {
-            std::fs::create_dir_all(output_dir).map_err(|_| ffmpeg::Error::Other { errno: -1 })?;
-        }
-        let file_suffix = input_file.extension().unwrap().to_str().unwrap();
-
-        let mut input_ctx = ffmpeg::format::input(input_file)?;
-        let audio_stream = input_ctx
-            .streams()
-            .best(ffmpeg::media::Type::Audio)
-            .ok_or(ffmpeg::Error::StreamNotFound)?;
-
-        let audio_parameters = audio_stream.parameters();
-        let codec =
-            ffmpeg::codec::context::Context::from_parameters(audio_parameters.clone()).unwrap();
-
-        let total_duration = input_ctx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
-        let num_chunks = (total_duration / self.chunk_duration as f64).ceil() as i64;
-
-        let input_time_base = audio_stream.time_base();
-        for chunk_index in 0..num_chunks {
-            let start_time = chunk_index * self.chunk_duration;
-            let output_filename = format!("chunk_{:03}.{}", chunk_index + 1, file_suffix);
-            let output_path = output_dir.join(output_filename);
-            let mut output_ctx = ffmpeg::format::output(&output_path)?;
-
-            let mut output_stream = output_ctx.add_stream(codec.codec())?;
-            output_stream.set_parameters(audio_parameters.clone());
-            output_stream.set_time_base(input_time_base);
-
-            let start_ts = start_time * ffmpeg::ffi::AV_TIME_BASE as i64;
+    // roll over to a new chunk before an output grows past `bytes`, whatever
+    // the duration estimate says, so every chunk fits an upload limit
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
 
-            // let start_ts = (start_time as f64 * input_time_base.denominator() as f64
-            //     / input_time_base.numerator() as f64) as i64;
-            let end_time = ((start_time + self.chunk_duration) * ffmpeg::ffi::AV_TIME_BASE as i64)
-                .min(input_ctx.duration());
+    // start every chunk but the first `seconds` before its cut, so the words
+    // around a cut are transcribed with context on both sides
+    pub fn with_overlap(mut self, seconds: f64) -> Self {
+        self.overlap = seconds.max(0.0);
+        self
+    }
 
-            input_ctx.seek(start_ts, ..start_ts)?;
+    // move every cut to the nearest silence instead of cutting mid-word
+    pub fn with_silence_search(mut self, config: SilenceConfig) -> Self {
+        self.silence = Some(config);
+        self
+    }
 
-            output_ctx.set_metadata(input_ctx.metadata().to_owned());
-            output_ctx.write_header()?;
+    // the times in seconds at which `split` will cut the input
+    pub fn cut_points(&self, input_file: &Path) -> Result<Vec<f64>, ffmpeg::Error> {
+        self.find_cuts(input_file, &CancelToken::new())
+    }
 
-            let mut last_pts = 0;
-            let mut pts_offset = 0;
-            let mut first_pts = None;
-            for (stream, packet) in input_ctx.packets() {
-                let pts = packet.pts().unwrap_or(0);
-                let current_time = pts * ffmpeg::ffi::AV_TIME_BASE as i64
-                    / stream.time_base().denominator() as i64;
+    fn find_cuts(
+        &self,
+        input_file: &Path,
+        cancel: &CancelToken,
+    ) -> Result<Vec<f64>, ffmpeg::Error> {
+        ffmpeg::init()?;
+        let input_ctx = ffmpeg::format::input(input_file)?;
+        let total_duration = input_ctx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
 
-                if current_time >= end_time {
-                    break;
-                }
-                let mut new_packet = packet.clone();
-                new_packet.set_position(-1);
-                new_packet.set_stream(0);
+        let targets: Vec<f64> = (1..)
+            .map(|i| (i * self.chunk_duration.max(1)) as f64)
+            .take_while(|&time| time < total_duration)
+            .collect();
+
+        let mut cuts = match &self.silence {
+            Some(config) => {
+                let mut decoder = MonoDecoder::open(input_file, silence::ANALYSIS_RATE)?;
+                silence::snap_to_silence(
+                    |start, end| {
+                        if cancel.is_cancelled() {
+                            return Err(ffmpeg::Error::Exit);
+                        }
+                        decoder.read_range(start, end)
+                    },
+                    &targets,
+                    config,
+                )?
+            }
+            None => targets,
+        };
+        // snapping can move neighbouring cuts onto each other with a wide tolerance
+        cuts.dedup_by(|next, prev| *next <= *prev + 1.0);
+        Ok(cuts)
+    }
 
-                if let Some(first_pts_value) = first_pts {
-                    let adjusted_pts = pts - first_pts_value;
+    pub fn split(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+    ) -> Result<Vec<ChunkInfo>, ffmpeg::Error> {
+        self.split_with_progress(input_file, output_dir, &CancelToken::new(), |_| {})
+    }
 
-                    if adjusted_pts < last_pts {
-                        pts_offset = last_pts;
-                    }
+    // `split` for a blocking thread, `on_progress` gets the whole percents of the
+    // input written so far and a set `cancel` ends it with `Error::Exit`
+    pub fn split_with_progress(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+        cancel: &CancelToken,
+        mut on_progress: impl FnMut(f64),
+    ) -> Result<Vec<ChunkInfo>, ffmpeg::Error> {
+        let cuts = self.find_cuts(input_file, cancel)?;
+        self.copy_chunks(input_file, output_dir, &cuts, cancel, &mut on_progress)
+    }
 
-                    let final_pts = adjusted_pts + pts_offset;
-                    new_packet.set_pts(Some(final_pts));
-                    new_packet.set_dts(Some(final_pts));
+    // copy the packets between the cut times into `chunk_001`, `chunk_002` ...
+    pub fn split_at(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+        cuts: &[f64],
+    ) -> Result<Vec<ChunkInfo>, ffmpeg::Error> {
+        self.copy_chunks(
+            input_file,
+            output_dir,
+            cuts,
+            &CancelToken::new(),
+            &mut |_| {},
+        )
+    }
 
-                    last_pts = final_pts;
-                } else {
-                    first_pts = Some(pts);
-                    new_packet.set_pts(Some(0));
-                    new_packet.set_dts(Some(0));
-                }
+    fn copy_chunks(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+        cuts: &[f64],
+        cancel: &CancelToken,
+        on_progress: &mut dyn FnMut(f64),
+    ) -> Result<Vec<ChunkInfo>, ffmpeg::Error> {
+        ffmpeg::init()?;
+        if !output_dir.is_dir() {
+            std::fs::create_dir_all(output_dir).map_err(|_| ffmpeg::Error::Other { errno: -1 })?;
+        }
+        let file_suffix = input_file.extension().unwrap().to_str().unwrap();
+        let mut copier = PacketCopier::open(input_file)?.with_cancel(cancel.clone());
+        let mut progress = Progress::new(cancel, on_progress, copier.duration());
+
+        let mut chunks = Vec::new();
+        let mut bounds = vec![f64::NEG_INFINITY];
+        bounds.extend_from_slice(cuts);
+        bounds.push(f64::INFINITY);
+
+        let mut chunk_index = 0;
+        while chunk_index + 1 < bounds.len() {
+            if progress.is_cancelled() {
+                return Err(ffmpeg::Error::Exit);
+            }
+            let (range_start, end_time) = (bounds[chunk_index], bounds[chunk_index + 1]);
+            let output_filename = format!("chunk_{:03}.{}", chunk_index + 1, file_suffix);
+            let output_path = output_dir.join(output_filename);
 
-                new_packet.write_interleaved(&mut output_ctx)?;
+            // never roll inside the overlap so every chunk makes progress
+            let limit = self.max_bytes.map(|max_bytes| SizeLimit {
+                max_bytes,
+                after: range_start,
+            });
+            // a size roll already read past the packet the next chunk starts with
+            let copied = copier.copy(
+                &output_path,
+                range_start - self.overlap,
+                end_time,
+                chunk_index > 0,
+                limit,
+            )?;
+            if let Some(rolled_at) = copied.rolled_at {
+                // the rest of the range becomes the next chunk
+                bounds.insert(chunk_index + 1, rolled_at);
             }
-            output_ctx.write_trailer()?;
+
+            let first_time = copied.first_time.unwrap_or(copied.last_end);
+            chunks.push(ChunkInfo {
+                path: output_path,
+                index: chunk_index,
+                start: (first_time - copier.stream_start()).max(0.0),
+                duration: (copied.last_end - first_time).max(0.0),
+            });
+            progress.report(copied.last_end - copier.stream_start());
+            chunk_index += 1;
         }
-        Ok(())
+        progress.finish();
+        Ok(chunks)
     }
 }
 
@@ -110,30 +288,62 @@ impl WavSplitter {
         }
     }
 
-    pub fn split_wav(&self, input_file: &Path, output_dir: &Path) -> Result<(), Box<dyn Error>> {
-        let reader = WavReader::open(input_file)?;
+    // copy the samples into `chunk_001.wav`, `chunk_002.wav` ... with the input's
+    // spec, reading one sample at a time so memory stays flat for long recordings
+    pub fn split_wav(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn Error>> {
+        let mut reader = WavReader::open(input_file)?;
         let spec = reader.spec();
-        let samples: Vec<i16> = reader.into_samples::<i16>().collect::<Result<_, _>>()?;
-
-        let sample_rate = spec.sample_rate;
-        let channels = spec.channels as usize;
-        let chunk_size = (self.chunk_duration * sample_rate * channels as u32) as usize;
 
         if !output_dir.is_dir() {
             fs::create_dir_all(output_dir)?;
         }
 
-        for (i, chunk) in samples.chunks(chunk_size).enumerate() {
+        match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Float, 32) => self.split_samples::<f32>(&mut reader, output_dir),
+            (SampleFormat::Int, 1..=8) => self.split_samples::<i8>(&mut reader, output_dir),
+            (SampleFormat::Int, 9..=16) => self.split_samples::<i16>(&mut reader, output_dir),
+            (SampleFormat::Int, 17..=32) => self.split_samples::<i32>(&mut reader, output_dir),
+            (format, bits) => Err(format!("unsupported wav format {:?} {}", format, bits).into()),
+        }
+    }
+
+    fn split_samples<S: Sample>(
+        &self,
+        reader: &mut WavReader<BufReader<File>>,
+        output_dir: &Path,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn Error>> {
+        let spec = reader.spec();
+        let channels = spec.channels as usize;
+        let chunk_size =
+            (self.chunk_duration as usize * spec.sample_rate as usize).max(1) * channels;
+        let samples_per_second = (spec.sample_rate as usize * channels) as f64;
+
+        let mut samples = reader.samples::<S>().peekable();
+        let mut chunks = Vec::new();
+        while samples.peek().is_some() {
+            let i = chunks.len();
             let output_filename = format!("chunk_{:03}.wav", i + 1);
             let output_file = output_dir.join(output_filename);
             let mut writer = WavWriter::create(&output_file, spec)?;
-            for &sample in chunk {
-                writer.write_sample(sample)?;
+            let mut written = 0;
+            for sample in samples.by_ref().take(chunk_size) {
+                writer.write_sample(sample?)?;
+                written += 1;
             }
             writer.finalize()?;
+            chunks.push(ChunkInfo {
+                path: output_file,
+                index: i,
+                start: (i * chunk_size) as f64 / samples_per_second,
+                duration: written as f64 / samples_per_second,
+            });
         }
 
-        Ok(())
+        Ok(chunks)
     }
 }
 
@@ -144,13 +354,58 @@ mod tests {
     use super::*;
 
     #[test]
+    #[ignore = "reads ./sample.wav, a local recording that is not committed"]
     fn split_audio_works() {
         let audio_splitter = AudioSplitter::new(60 * 10);
         // let input_file = PathBuf::from_str("./sample.webm").unwrap();
         let input_file = PathBuf::from_str("./sample.wav").unwrap();
         let output_dir = PathBuf::from_str("output_dir").unwrap();
-        let result = audio_splitter.split(&input_file, &output_dir);
-        assert!(result.is_ok());
+        let chunks = audio_splitter.split(&input_file, &output_dir).unwrap();
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[0].index + 1, pair[1].index);
+            assert!((pair[0].end() - pair[1].start).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.wav, a local recording that is not committed"]
+    fn split_audio_at_silence_works() {
+        let audio_splitter =
+            AudioSplitter::new(60 * 10).with_silence_search(SilenceConfig::default());
+        let input_file = PathBuf::from_str("./sample.wav").unwrap();
+        let cuts = audio_splitter.cut_points(&input_file).unwrap();
+        for (i, cut) in cuts.iter().enumerate() {
+            let target = ((i + 1) * 60 * 10) as f64;
+            assert!((cut - target).abs() <= SilenceConfig::default().tolerance);
+        }
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.wav, a local recording that is not committed"]
+    fn split_audio_with_overlap_works() {
+        let audio_splitter = AudioSplitter::new(60 * 10).with_overlap(3.0);
+        let input_file = PathBuf::from_str("./sample.wav").unwrap();
+        let output_dir = PathBuf::from_str("output_dir").unwrap();
+        let chunks = audio_splitter.split(&input_file, &output_dir).unwrap();
+        for pair in chunks.windows(2) {
+            assert!((pair[0].end() - pair[1].start - 3.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn split_audio_by_size_works() {
+        let max_bytes = 2 * 1024 * 1024;
+        let audio_splitter = AudioSplitter::new(60 * 60).with_max_bytes(max_bytes);
+        let input_file = PathBuf::from_str("./sample.m4a").unwrap();
+        let output_dir = PathBuf::from_str("output_dir").unwrap();
+        let chunks = audio_splitter.split(&input_file, &output_dir).unwrap();
+        for chunk in &chunks {
+            assert!(fs::metadata(&chunk.path).unwrap().len() <= max_bytes);
+        }
+        for pair in chunks.windows(2) {
+            assert!((pair[0].end() - pair[1].start).abs() < 0.1);
+        }
     }
 
     #[test]
@@ -158,10 +413,102 @@ mod tests {
         let wav_splitter = WavSplitter::new(300);
         let input_file = PathBuf::from_str("./output.wav").unwrap();
         let output_dir = PathBuf::from_str("output_dir").unwrap();
-        let result = wav_splitter.split_wav(&input_file, &output_dir);
-        assert!(result.is_ok());
+        let chunks = wav_splitter.split_wav(&input_file, &output_dir).unwrap();
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.index, i);
+            assert_eq!(chunk.start, (i * 300) as f64);
+        }
+    }
+
+    #[test]
+    fn split_wav_streams_wide_formats() {
+        let output_dir = PathBuf::from_str("output_dir/wide").unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        let formats = [(SampleFormat::Int, 24), (SampleFormat::Float, 32)];
+        for (sample_format, bits_per_sample) in formats {
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: 8000,
+                bits_per_sample,
+                sample_format,
+            };
+            let input_file = output_dir.join(format!("input_{}.wav", bits_per_sample));
+            let mut writer = WavWriter::create(&input_file, spec).unwrap();
+            // 2.5 seconds of stereo frames
+            for i in 0..20000 * 2 {
+                match sample_format {
+                    SampleFormat::Int => writer.write_sample(i * 100 - 4_000_000).unwrap(),
+                    SampleFormat::Float => writer.write_sample(i as f32 / 40000.0).unwrap(),
+                }
+            }
+            writer.finalize().unwrap();
+
+            let chunk_dir = output_dir.join(format!("chunks_{}", bits_per_sample));
+            let chunks = WavSplitter::new(1)
+                .split_wav(&input_file, &chunk_dir)
+                .unwrap();
+            assert_eq!(chunks.len(), 3);
+            assert_eq!(chunks[2].start, 2.0);
+            assert_eq!(chunks[2].duration, 0.5);
+
+            let reader = WavReader::open(&chunks[1].path).unwrap();
+            assert_eq!(reader.spec(), spec);
+            assert_eq!(reader.len(), 16000);
+        }
+    }
+
+    #[test]
+    fn split_with_progress_reports_and_cancels() {
+        let output_dir = PathBuf::from_str("output_dir/progress").unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let input_file = output_dir.join("input.wav");
+        let mut writer = WavWriter::create(&input_file, spec).unwrap();
+        // 10 seconds of a 440 Hz tone
+        for i in 0..80000 {
+            let time = i as f32 / 8000.0;
+            let sample = (time * 440.0 * std::f32::consts::TAU).sin() * 8000.0;
+            writer.write_sample(sample as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let splitter = AudioSplitter::new(3);
+        let mut reported = Vec::new();
+        let chunks = splitter
+            .split_with_progress(
+                &input_file,
+                &output_dir.join("chunks"),
+                &CancelToken::new(),
+                |percent| reported.push(percent),
+            )
+            .unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert!(reported.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reported.last(), Some(&100.0));
+
+        // cancelled once the first chunk is done
+        let cancel = CancelToken::new();
+        let mut reported = Vec::new();
+        let result = splitter.split_with_progress(
+            &input_file,
+            &output_dir.join("cancelled"),
+            &cancel,
+            |percent| {
+                reported.push(percent);
+                cancel.cancel();
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0] < 100.0);
     }
 
+    #[cfg(feature = "ffmpeg")]
     #[test]
     fn test_ffmpeg() {
         ffmpeg::init().unwrap();
@@ -0,0 +1,202 @@
+// a backend without libav for the `pure-rust` feature, symphonia demuxes webm,
+// m4a, mp3 and wav and decodes everything but opus, webm-iterable writes webm
+mod resample;
+mod source;
+mod split;
+mod webm;
+
+use crate::{
+    ChunkInfo, MediaInfo, StreamInfo, StreamKind, SubtitleCue, SubtitleTrack, Thumbnail,
+    ThumbnailConfig,
+};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use source::{seconds, to_pcm16, Source};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use symphonia::core::codecs::{
+    CodecType, CODEC_TYPE_FLAC, CODEC_TYPE_NULL, CODEC_TYPE_OPUS, CODEC_TYPE_VORBIS,
+};
+use symphonia::core::formats::Track;
+
+pub(crate) use source::MonoDecoder;
+pub use split::{extract_clip, AudioSplitter};
+
+// symphonia has no name for the format it picked, use ffmpeg's demuxer names
+fn container_name(path: &Path) -> String {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "webm" | "mkv" | "mka" => "matroska,webm".to_string(),
+        "m4a" | "mp4" | "m4b" | "mov" => "mov,mp4,m4a,3gp,3g2,mj2".to_string(),
+        _ => extension,
+    }
+}
+
+fn codec_name(codec: CodecType) -> String {
+    if let Some(descriptor) = symphonia::default::get_codecs().get_codec(codec) {
+        return descriptor.short_name.to_string();
+    }
+    // known to the demuxers but not built in as decoders
+    match codec {
+        CODEC_TYPE_OPUS => "opus",
+        CODEC_TYPE_VORBIS => "vorbis",
+        CODEC_TYPE_FLAC => "flac",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+fn stream_info(index: usize, track: &Track) -> StreamInfo {
+    let params = &track.codec_params;
+    let kind = if params.codec == CODEC_TYPE_NULL {
+        StreamKind::Other
+    } else {
+        StreamKind::Audio
+    };
+    StreamInfo {
+        index,
+        kind,
+        codec: codec_name(params.codec),
+        duration: params
+            .time_base
+            .zip(params.n_frames)
+            .map(|(time_base, frames)| seconds(time_base, frames)),
+        bit_rate: 0,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count() as u16),
+        width: None,
+        height: None,
+        language: track.language.clone(),
+        tags: HashMap::new(),
+    }
+}
+
+pub fn probe(path: &Path) -> Result<MediaInfo, Box<dyn Error>> {
+    let mut source = Source::open(path)?;
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let tracks = source.format.tracks();
+    let streams: Vec<StreamInfo> = tracks
+        .iter()
+        .enumerate()
+        .map(|(index, track)| stream_info(index, track))
+        .collect();
+    let best_audio = tracks.iter().position(|track| track.id == source.track_id);
+
+    // webm from youtube has no frame count, time it by its last packet
+    let duration = match source.duration() {
+        Some(duration) => duration,
+        None => {
+            let mut end = 0.0;
+            while let Some(packet) = source.next_packet()? {
+                end = source.time(packet.ts() + packet.dur());
+            }
+            end
+        }
+    };
+    let bit_rate = if duration > 0.0 {
+        (size as f64 * 8.0 / duration) as i64
+    } else {
+        0
+    };
+
+    Ok(MediaInfo {
+        container: container_name(path),
+        duration,
+        size,
+        bit_rate,
+        streams,
+        tags: source.tags,
+        best_audio,
+    })
+}
+
+// symphonia only demuxes audio, subtitles always look absent
+pub fn list_subtitle_tracks(_input_file: &Path) -> Result<Vec<SubtitleTrack>, Box<dyn Error>> {
+    Ok(Vec::new())
+}
+
+pub fn extract_subtitles(
+    _input_file: &Path,
+    _stream_index: usize,
+) -> Result<Vec<SubtitleCue>, Box<dyn Error>> {
+    Err("subtitle streams need the ffmpeg feature".into())
+}
+
+// symphonia has no video decoders
+pub fn extract_keyframes(
+    _input_file: &Path,
+    _times: &[f64],
+    _output_dir: &Path,
+    _config: &ThumbnailConfig,
+) -> Result<Vec<Thumbnail>, Box<dyn Error>> {
+    Err("keyframe extraction needs the ffmpeg feature".into())
+}
+
+// decode the audio of `input_file` to 16 bit mono pcm at `sample_rate`, the stand
+// in for `Transcoder` when libav is missing
+pub fn resample_to_wav(
+    input_file: &Path,
+    output_file: &Path,
+    sample_rate: u32,
+) -> Result<ChunkInfo, Box<dyn Error>> {
+    let mut decoder = MonoDecoder::open(input_file, sample_rate)?;
+    if let Some(parent) = output_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(output_file, spec)?;
+    let mut written = 0;
+    while let Some((_, samples)) = decoder.next_batch()? {
+        for &sample in &samples {
+            writer.write_sample(to_pcm16(sample))?;
+        }
+        written += samples.len();
+    }
+    writer.finalize()?;
+
+    Ok(ChunkInfo::whole(
+        output_file.to_path_buf(),
+        written as f64 / sample_rate as f64,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn probe_works() {
+        let info = probe(Path::new("./sample.m4a")).unwrap();
+        assert!(info.container.contains("m4a"));
+        assert!(info.duration > 0.0);
+
+        let audio = info.audio().unwrap();
+        assert_eq!(audio.kind, StreamKind::Audio);
+        assert_eq!(audio.codec, "aac");
+        assert!(audio.sample_rate.unwrap() > 0);
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn resample_to_wav_works() {
+        let input_file = Path::new("./sample.m4a");
+        let output_file = Path::new("output_dir/resampled.wav");
+        let chunk = resample_to_wav(input_file, output_file, 16000).unwrap();
+        assert!((chunk.duration - probe(input_file).unwrap().duration).abs() < 0.5);
+
+        let reader = hound::WavReader::open(output_file).unwrap();
+        assert_eq!(reader.spec().sample_rate, 16000);
+        assert_eq!(reader.spec().channels, 1);
+    }
+}
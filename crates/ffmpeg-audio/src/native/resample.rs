@@ -0,0 +1,130 @@
+use std::f64::consts::PI;
+
+// zero crossings of the sinc kept on each side of an output sample
+const HALF_TAPS: f64 = 16.0;
+
+// windowed sinc resampler for a mono stream, output sample `n` sits exactly on
+// input position `n * step` so timestamps carry over without a delay
+pub(crate) struct Resampler {
+    // input samples per output sample
+    step: f64,
+    // fraction of the input band kept, below 1 when downsampling
+    cutoff: f64,
+    half_width: usize,
+    // input not yet fully used, starts with `half_width` zeros of left context
+    buffer: Vec<f32>,
+    // where the next output sample falls in `buffer`
+    position: f64,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        let step = from_rate as f64 / to_rate.max(1) as f64;
+        let cutoff = (1.0 / step).min(1.0);
+        let half_width = (HALF_TAPS / cutoff).ceil() as usize;
+        Self {
+            step,
+            cutoff,
+            half_width,
+            buffer: vec![0.0; half_width],
+            position: half_width as f64,
+        }
+    }
+
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.buffer.extend_from_slice(input);
+        // the last `half_width` samples are lookahead for outputs still to come
+        let end = self.buffer.len() - self.half_width;
+        self.drain(end, output);
+    }
+
+    // flush the samples held back as lookahead, the stream ends here
+    pub fn finish(&mut self, output: &mut Vec<f32>) {
+        let end = self.buffer.len();
+        self.buffer.resize(end + self.half_width, 0.0);
+        self.drain(end, output);
+    }
+
+    fn drain(&mut self, end: usize, output: &mut Vec<f32>) {
+        while self.position < end as f64 {
+            output.push(self.sample_at(self.position));
+            self.position += self.step;
+        }
+        let used = (self.position as usize).saturating_sub(self.half_width);
+        self.buffer.drain(..used);
+        self.position -= used as f64;
+    }
+
+    fn sample_at(&self, position: f64) -> f32 {
+        let center = position as usize;
+        let first = center + 1 - self.half_width;
+        let last = (center + self.half_width).min(self.buffer.len() - 1);
+        let width = self.half_width as f64;
+        let mut sum = 0.0;
+        for (i, &sample) in self.buffer[first..=last].iter().enumerate() {
+            let distance = position - (first + i) as f64;
+            let x = distance * self.cutoff;
+            let sinc = if x.abs() < 1e-9 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 * (1.0 + (PI * distance / width).cos());
+            sum += sample as f64 * self.cutoff * sinc * window;
+        }
+        sum as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resample_all(resampler: &mut Resampler, input: &[f32]) -> Vec<f32> {
+        let mut output = Vec::new();
+        for batch in input.chunks(1000) {
+            resampler.process(batch, &mut output);
+        }
+        resampler.finish(&mut output);
+        output
+    }
+
+    #[test]
+    fn same_rate_is_identity() {
+        let input: Vec<f32> = (0..5000)
+            .map(|i| ((i * 7919) % 200) as f32 / 100.0 - 1.0)
+            .collect();
+        let output = resample_all(&mut Resampler::new(16000, 16000), &input);
+        assert_eq!(output.len(), input.len());
+        for (a, b) in input.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn downsampling_keeps_tones_and_timing() {
+        // a 440 Hz tone at 48 kHz, one second
+        let tone = |rate: f64, i: usize| (2.0 * PI * 440.0 * i as f64 / rate).sin() as f32 * 0.5;
+        let input: Vec<f32> = (0..48000).map(|i| tone(48000.0, i)).collect();
+        let output = resample_all(&mut Resampler::new(48000, 16000), &input);
+        assert_eq!(output.len(), 16000);
+        // away from the edges every sample lands where the tone is at 16 kHz
+        for (i, &sample) in output.iter().enumerate().take(15900).skip(100) {
+            assert!((sample - tone(16000.0, i)).abs() < 0.01, "{} {}", i, sample);
+        }
+    }
+
+    #[test]
+    fn downsampling_drops_content_above_nyquist() {
+        // 6 kHz is above the 4 kHz nyquist of the output
+        let input: Vec<f32> = (0..44100)
+            .map(|i| (2.0 * PI * 6000.0 * i as f64 / 44100.0).sin() as f32)
+            .collect();
+        let output = resample_all(&mut Resampler::new(44100, 8000), &input);
+        assert_eq!(output.len(), 8000);
+        let peak = output[100..7900]
+            .iter()
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak < 0.05, "{}", peak);
+    }
+}
@@ -0,0 +1,247 @@
+use super::resample::Resampler;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{
+    CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS,
+};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, Packet, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision};
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
+
+pub(crate) fn seconds(time_base: TimeBase, ts: u64) -> f64 {
+    let time = time_base.calc_time(ts);
+    time.seconds as f64 + time.frac
+}
+
+pub(crate) fn to_pcm16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn collect_tags(revision: &MetadataRevision, tags: &mut HashMap<String, String>) {
+    for tag in revision.tags() {
+        tags.insert(tag.key.clone(), tag.value.to_string());
+    }
+}
+
+// the first audio track of a file, demuxed by symphonia
+pub(crate) struct Source {
+    pub format: Box<dyn FormatReader>,
+    pub track_id: u32,
+    pub params: CodecParameters,
+    pub time_base: TimeBase,
+    // container tags plus the ones read while probing, e.g. ID3 in front of an mp3
+    pub tags: HashMap<String, String>,
+}
+
+impl Source {
+    pub fn open(input_file: &Path) -> Result<Self, Box<dyn Error>> {
+        let stream = MediaSourceStream::new(Box::new(File::open(input_file)?), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = input_file.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(extension);
+        }
+        let mut probed = symphonia::default::get_probe().format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+
+        let mut tags = HashMap::new();
+        if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+            collect_tags(revision, &mut tags);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            collect_tags(revision, &mut tags);
+        }
+
+        let track = probed
+            .format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or("no audio track found")?;
+        let params = track.codec_params.clone();
+        let time_base = params
+            .time_base
+            .or_else(|| params.sample_rate.map(|rate| TimeBase::new(1, rate)))
+            .ok_or("the audio track has no time base")?;
+
+        Ok(Self {
+            track_id: track.id,
+            format: probed.format,
+            params,
+            time_base,
+            tags,
+        })
+    }
+
+    pub fn time(&self, ts: u64) -> f64 {
+        seconds(self.time_base, ts)
+    }
+
+    // from the frame count in the header, none when the container leaves it out
+    pub fn duration(&self) -> Option<f64> {
+        self.params.n_frames.map(|frames| self.time(frames))
+    }
+
+    // the next packet of the track, none at the end of the file
+    pub fn next_packet(&mut self) -> Result<Option<Packet>, Box<dyn Error>> {
+        loop {
+            match self.format.next_packet() {
+                Ok(packet) if packet.track_id() == self.track_id => return Ok(Some(packet)),
+                Ok(_) => continue,
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // lands on or before `time`, on a packet boundary
+    pub fn seek(&mut self, time: f64) -> Result<(), Box<dyn Error>> {
+        self.format.seek(
+            SeekMode::Coarse,
+            SeekTo::Time {
+                time: Time::from(time.max(0.0)),
+                track_id: Some(self.track_id),
+            },
+        )?;
+        Ok(())
+    }
+}
+
+// decodes the track to mono f32 samples at a fixed rate, the counterpart of the
+// ffmpeg backend's decoder
+pub(crate) struct MonoDecoder {
+    source: Source,
+    decoder: Box<dyn Decoder>,
+    sample_rate: u32,
+    // made for the rate of the first decoded buffer
+    resampler: Option<Resampler>,
+    // time of the next output sample, unknown until a packet is decoded after a seek
+    next_time: Option<f64>,
+}
+
+impl MonoDecoder {
+    pub fn open(input_file: &Path, sample_rate: u32) -> Result<Self, Box<dyn Error>> {
+        let source = Source::open(input_file)?;
+        let decoder = symphonia::default::get_codecs()
+            .make(&source.params, &DecoderOptions::default())
+            .map_err(|e| -> Box<dyn Error> {
+                match source.params.codec {
+                    CODEC_TYPE_OPUS => {
+                        "opus has no pure Rust decoder, build with the ffmpeg feature".into()
+                    }
+                    _ => e.into(),
+                }
+            })?;
+        Ok(Self {
+            source,
+            decoder,
+            sample_rate,
+            resampler: None,
+            next_time: None,
+        })
+    }
+
+    pub fn seek(&mut self, time: f64) -> Result<(), Box<dyn Error>> {
+        self.source.seek(time)?;
+        self.decoder.reset();
+        self.resampler = None;
+        self.next_time = None;
+        Ok(())
+    }
+
+    // the next samples with the time of the first one, none at the end of the file
+    pub fn next_batch(&mut self) -> Result<Option<(f64, Vec<f32>)>, Box<dyn Error>> {
+        let mut output = Vec::new();
+        while output.is_empty() {
+            let packet = match self.source.next_packet()? {
+                Some(packet) => packet,
+                None => {
+                    // the resampler holds back a little lookahead
+                    match self.resampler.take() {
+                        Some(mut resampler) => resampler.finish(&mut output),
+                        None => return Ok(None),
+                    }
+                    continue;
+                }
+            };
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // a corrupt frame is dropped rather than failing the whole file
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let spec = *decoded.spec();
+            let channels = spec.channels.count().max(1);
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            let mono: Vec<f32> = buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect();
+
+            self.next_time
+                .get_or_insert_with(|| self.source.time(packet.ts()));
+            let sample_rate = self.sample_rate;
+            self.resampler
+                .get_or_insert_with(|| Resampler::new(spec.rate, sample_rate))
+                .process(&mono, &mut output);
+        }
+
+        let time = self.next_time.unwrap_or(0.0);
+        self.next_time = Some(time + output.len() as f64 / self.sample_rate as f64);
+        Ok(Some((time, output)))
+    }
+
+    // feed the samples in `[start, end)` to `on_samples` together with the time of
+    // the first sample of each batch, stop early when it returns false
+    pub fn read<F>(&mut self, start: f64, end: f64, mut on_samples: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(f64, &[f32]) -> bool,
+    {
+        if start > 0.0 {
+            self.seek(start)?;
+        }
+        let rate = self.sample_rate as f64;
+        while let Some((time, samples)) = self.next_batch()? {
+            let skip = ((start - time) * rate).ceil().max(0.0) as usize;
+            if skip >= samples.len() {
+                continue;
+            }
+            let batch_time = time + skip as f64 / rate;
+            if batch_time >= end {
+                break;
+            }
+            let take = (((end - batch_time) * rate).ceil() as usize).min(samples.len() - skip);
+            if !on_samples(batch_time, &samples[skip..skip + take]) || skip + take < samples.len() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // collect the samples in `[start, end)`, returns the time of the first one
+    pub fn read_range(&mut self, start: f64, end: f64) -> Result<(f64, Vec<f32>), Box<dyn Error>> {
+        let mut first_time = None;
+        let mut samples = Vec::new();
+        self.read(start, end, |time, batch| {
+            first_time.get_or_insert(time);
+            samples.extend_from_slice(batch);
+            true
+        })?;
+        Ok((first_time.unwrap_or(start), samples))
+    }
+}
@@ -0,0 +1,435 @@
+use super::source::{to_pcm16, MonoDecoder, Source};
+use super::webm::WebmChunkWriter;
+use crate::progress::Progress;
+use crate::silence::{self, SilenceConfig, ANALYSIS_RATE};
+use crate::{CancelToken, ChunkInfo, CONTAINER_OVERHEAD, PACKET_OVERHEAD, SPEECH_SAMPLE_RATE};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use symphonia::core::codecs::{CODEC_TYPE_MP3, CODEC_TYPE_OPUS, CODEC_TYPE_VORBIS};
+
+// a packet, or a batch of decoded samples, times are on the input stream's timeline
+struct Unit {
+    time: f64,
+    end: f64,
+    payload: Payload,
+}
+
+enum Payload {
+    Packet(Vec<u8>),
+    Samples(Vec<i16>),
+}
+
+impl Unit {
+    fn size(&self) -> u64 {
+        match &self.payload {
+            Payload::Packet(data) => data.len() as u64 + PACKET_OVERHEAD,
+            Payload::Samples(samples) => samples.len() as u64 * 2,
+        }
+    }
+}
+
+// how the chunks are written, picked from the input codec
+enum Output {
+    // opus and vorbis packets are copied into webm
+    Webm {
+        codec_id: &'static str,
+        codec_private: Option<Vec<u8>>,
+        sample_rate: f64,
+        channels: u64,
+    },
+    // mp3 frames carry their own headers and are copied as they are
+    Mp3,
+    // anything else is decoded to 16 kHz mono pcm
+    Wav,
+}
+
+enum Input {
+    Packets(Source),
+    Decoded(MonoDecoder),
+}
+
+impl Output {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Webm { .. } => "webm",
+            Self::Mp3 => "mp3",
+            Self::Wav => "wav",
+        }
+    }
+}
+
+impl Input {
+    fn open(input_file: &Path) -> Result<(Self, Output), Box<dyn Error>> {
+        let source = Source::open(input_file)?;
+        let params = &source.params;
+        let codec_id = match params.codec {
+            CODEC_TYPE_OPUS => Some("A_OPUS"),
+            CODEC_TYPE_VORBIS => Some("A_VORBIS"),
+            _ => None,
+        };
+        let output = match codec_id {
+            Some(codec_id) => Output::Webm {
+                codec_id,
+                codec_private: params.extra_data.as_ref().map(|data| data.to_vec()),
+                sample_rate: params.sample_rate.unwrap_or(48000) as f64,
+                channels: params
+                    .channels
+                    .map_or(1, |channels| channels.count() as u64),
+            },
+            None if params.codec == CODEC_TYPE_MP3 => Output::Mp3,
+            None => {
+                let decoder = MonoDecoder::open(input_file, SPEECH_SAMPLE_RATE)?;
+                return Ok((Self::Decoded(decoder), Output::Wav));
+            }
+        };
+        Ok((Self::Packets(source), output))
+    }
+
+    fn next(&mut self) -> Result<Option<Unit>, Box<dyn Error>> {
+        match self {
+            Self::Packets(source) => {
+                let packet = match source.next_packet()? {
+                    Some(packet) => packet,
+                    None => return Ok(None),
+                };
+                Ok(Some(Unit {
+                    time: source.time(packet.ts()),
+                    end: source.time(packet.ts() + packet.dur()),
+                    payload: Payload::Packet(packet.data.into_vec()),
+                }))
+            }
+            Self::Decoded(decoder) => {
+                let (time, samples) = match decoder.next_batch()? {
+                    Some(batch) => batch,
+                    None => return Ok(None),
+                };
+                Ok(Some(Unit {
+                    time,
+                    end: time + samples.len() as f64 / SPEECH_SAMPLE_RATE as f64,
+                    payload: Payload::Samples(samples.into_iter().map(to_pcm16).collect()),
+                }))
+            }
+        }
+    }
+}
+
+enum ChunkWriter {
+    Webm(WebmChunkWriter),
+    Mp3(BufWriter<File>),
+    Wav(WavWriter<BufWriter<File>>),
+}
+
+impl ChunkWriter {
+    fn create(output: &Output, output_file: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(match output {
+            Output::Webm {
+                codec_id,
+                codec_private,
+                sample_rate,
+                channels,
+            } => Self::Webm(WebmChunkWriter::create(
+                output_file,
+                codec_id,
+                codec_private.as_deref(),
+                *sample_rate,
+                *channels,
+            )?),
+            Output::Mp3 => Self::Mp3(BufWriter::new(File::create(output_file)?)),
+            Output::Wav => {
+                let spec = WavSpec {
+                    channels: 1,
+                    sample_rate: SPEECH_SAMPLE_RATE,
+                    bits_per_sample: 16,
+                    sample_format: SampleFormat::Int,
+                };
+                Self::Wav(WavWriter::create(output_file, spec)?)
+            }
+        })
+    }
+
+    fn write(&mut self, unit: &Unit, first_time: f64) -> Result<(), Box<dyn Error>> {
+        match (self, &unit.payload) {
+            (Self::Webm(writer), Payload::Packet(data)) => {
+                let time = ((unit.time - first_time).max(0.0) * 1000.0).round() as u64;
+                writer.write_frame(time, data)?;
+            }
+            (Self::Mp3(writer), Payload::Packet(data)) => writer.write_all(data)?,
+            (Self::Wav(writer), Payload::Samples(samples)) => {
+                for &sample in samples {
+                    writer.write_sample(sample)?;
+                }
+            }
+            _ => return Err("the payload does not match the chunk format".into()),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Self::Webm(writer) => writer.finish()?,
+            Self::Mp3(mut writer) => writer.flush()?,
+            Self::Wav(writer) => writer.finalize()?,
+        }
+        Ok(())
+    }
+}
+
+// the chunk being written and what went into it so far
+struct OpenChunk {
+    writer: ChunkWriter,
+    path: PathBuf,
+    index: usize,
+    first_time: Option<f64>,
+    last_end: f64,
+    written: u64,
+}
+
+impl OpenChunk {
+    fn create(output: &Output, path: PathBuf, index: usize) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            writer: ChunkWriter::create(output, &path)?,
+            path,
+            index,
+            first_time: None,
+            last_end: 0.0,
+            written: CONTAINER_OVERHEAD,
+        })
+    }
+
+    fn write(&mut self, unit: &Unit) -> Result<(), Box<dyn Error>> {
+        let first_time = *self.first_time.get_or_insert(unit.time);
+        self.writer.write(unit, first_time)?;
+        self.last_end = unit.end;
+        self.written += unit.size();
+        Ok(())
+    }
+
+    fn finish(self, stream_start: f64) -> Result<ChunkInfo, Box<dyn Error>> {
+        self.writer.finish()?;
+        let first_time = self.first_time.unwrap_or(self.last_end);
+        Ok(ChunkInfo {
+            path: self.path,
+            index: self.index,
+            start: (first_time - stream_start).max(0.0),
+            duration: (self.last_end - first_time).max(0.0),
+        })
+    }
+}
+
+// the pure Rust counterpart of the ffmpeg backend's splitter, with the same options
+#[derive(Debug, Clone)]
+pub struct AudioSplitter {
+    chunk_duration: i64,
+    silence: Option<SilenceConfig>,
+    overlap: f64,
+    max_bytes: Option<u64>,
+}
+
+impl AudioSplitter {
+    pub fn new(duration_seconds: i64) -> Self {
+        Self {
+            chunk_duration: duration_seconds,
+            silence: None,
+            overlap: 0.0,
+            max_bytes: None,
+        }
+    }
+
+    // roll over to a new chunk before an output grows past `bytes`
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    // start every chunk but the first `seconds` before its cut
+    pub fn with_overlap(mut self, seconds: f64) -> Self {
+        self.overlap = seconds.max(0.0);
+        self
+    }
+
+    // move every cut to the nearest silence, only for inputs that can be decoded
+    pub fn with_silence_search(mut self, config: SilenceConfig) -> Self {
+        self.silence = Some(config);
+        self
+    }
+
+    // the times in seconds at which `split` will cut the input
+    pub fn cut_points(&self, input_file: &Path) -> Result<Vec<f64>, Box<dyn Error>> {
+        let total_duration = super::probe(input_file)?.duration;
+        self.find_cuts(input_file, total_duration, &CancelToken::new())
+    }
+
+    fn find_cuts(
+        &self,
+        input_file: &Path,
+        total_duration: f64,
+        cancel: &CancelToken,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        let targets: Vec<f64> = (1..)
+            .map(|i| (i * self.chunk_duration.max(1)) as f64)
+            .take_while(|&time| time < total_duration)
+            .collect();
+
+        let mut cuts = match &self.silence {
+            Some(config) => {
+                let mut decoder = MonoDecoder::open(input_file, ANALYSIS_RATE)?;
+                silence::snap_to_silence(
+                    |start, end| {
+                        if cancel.is_cancelled() {
+                            return Err("split cancelled".into());
+                        }
+                        decoder.read_range(start, end)
+                    },
+                    &targets,
+                    config,
+                )?
+            }
+            None => targets,
+        };
+        cuts.dedup_by(|next, prev| *next <= *prev + 1.0);
+        Ok(cuts)
+    }
+
+    pub fn split(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn Error>> {
+        self.split_with_progress(input_file, output_dir, &CancelToken::new(), |_| {})
+    }
+
+    // `split` for a blocking thread, `on_progress` gets the whole percents of the
+    // input written so far and a set `cancel` ends it with an error
+    pub fn split_with_progress(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+        cancel: &CancelToken,
+        mut on_progress: impl FnMut(f64),
+    ) -> Result<Vec<ChunkInfo>, Box<dyn Error>> {
+        let total_duration = super::probe(input_file)?.duration;
+        let cuts = self.find_cuts(input_file, total_duration, cancel)?;
+        let mut progress = Progress::new(cancel, &mut on_progress, total_duration);
+        self.write_chunks(input_file, output_dir, &cuts, &mut progress)
+    }
+
+    // write the audio between the cut times into `chunk_001`, `chunk_002` ... in a
+    // single pass, the overlap is replayed from the units kept in memory
+    pub fn split_at(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+        cuts: &[f64],
+    ) -> Result<Vec<ChunkInfo>, Box<dyn Error>> {
+        let cancel = CancelToken::new();
+        // nothing listens, so no probe for the total duration
+        let mut progress = Progress::new(&cancel, &mut |_| {}, 0.0);
+        self.write_chunks(input_file, output_dir, cuts, &mut progress)
+    }
+
+    fn write_chunks(
+        &self,
+        input_file: &Path,
+        output_dir: &Path,
+        cuts: &[f64],
+        progress: &mut Progress,
+    ) -> Result<Vec<ChunkInfo>, Box<dyn Error>> {
+        fs::create_dir_all(output_dir)?;
+        let (mut input, output) = Input::open(input_file)?;
+
+        let mut bounds = vec![f64::NEG_INFINITY];
+        bounds.extend_from_slice(cuts);
+        bounds.push(f64::INFINITY);
+
+        let mut chunks = Vec::new();
+        let mut history: VecDeque<Unit> = VecDeque::new();
+        let chunk_path = |index: usize| {
+            output_dir.join(format!("chunk_{:03}.{}", index + 1, output.extension()))
+        };
+        let mut chunk = OpenChunk::create(&output, chunk_path(0), 0)?;
+        let mut stream_start = None;
+        while let Some(unit) = input.next()? {
+            if progress.is_cancelled() {
+                return Err("split cancelled".into());
+            }
+            let start = *stream_start.get_or_insert(unit.time);
+            loop {
+                let range_start = bounds[chunk.index];
+                // never roll inside the overlap so every chunk makes progress
+                let full = self.max_bytes.is_some_and(|max_bytes| {
+                    chunk.written + unit.size() > max_bytes
+                        && chunk.first_time.is_some()
+                        && unit.time > range_start
+                });
+                if full {
+                    // the rest of the range becomes the next chunk
+                    bounds.insert(chunk.index + 1, unit.time);
+                }
+                if unit.time < bounds[chunk.index + 1] {
+                    break;
+                }
+
+                let index = chunk.index + 1;
+                let next = OpenChunk::create(&output, chunk_path(index), index)?;
+                chunks.push(std::mem::replace(&mut chunk, next).finish(start)?);
+                let overlap_start = bounds[index] - self.overlap;
+                for past in history.iter().filter(|past| past.time >= overlap_start) {
+                    chunk.write(past)?;
+                }
+            }
+            chunk.write(&unit)?;
+            progress.report(unit.end - start);
+
+            let keep_from = unit.time - self.overlap;
+            history.push_back(unit);
+            while history.front().is_some_and(|past| past.time < keep_from) {
+                history.pop_front();
+            }
+        }
+        chunks.push(chunk.finish(stream_start.unwrap_or(0.0))?);
+        progress.finish();
+        Ok(chunks)
+    }
+}
+
+// cut `[start, end)` seconds out of `input_file`, the output is written in the
+// format `AudioSplitter` would use for the input and its extension has to match
+pub fn extract_clip(
+    input_file: &Path,
+    output_file: &Path,
+    start: f64,
+    end: f64,
+) -> Result<ChunkInfo, Box<dyn Error>> {
+    if end <= start || start.is_nan() || end.is_nan() {
+        return Err("empty clip range".into());
+    }
+    let (mut input, output) = Input::open(input_file)?;
+    let extension = output_file.extension().and_then(|ext| ext.to_str());
+    if !extension.is_some_and(|ext| ext.eq_ignore_ascii_case(output.extension())) {
+        return Err(format!(
+            "clips of this input are written as .{}, converting needs the ffmpeg feature",
+            output.extension()
+        )
+        .into());
+    }
+    if let Some(parent) = output_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut chunk = OpenChunk::create(&output, output_file.to_path_buf(), 0)?;
+    let mut stream_start = None;
+    while let Some(unit) = input.next()? {
+        let time = unit.time - *stream_start.get_or_insert(unit.time);
+        if time >= end {
+            break;
+        }
+        if time >= start {
+            chunk.write(&unit)?;
+        }
+    }
+    chunk.finish(stream_start.unwrap_or(0.0))
+}
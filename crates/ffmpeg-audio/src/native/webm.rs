@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use webm_iterable::matroska_spec::{Master, MatroskaSpec};
+use webm_iterable::WebmWriter;
+
+// block times are i16 millisecond offsets from their cluster, start a new
+// cluster long before that overflows
+const CLUSTER_SPAN_MS: u64 = 5_000;
+const TRACK_NUMBER: u64 = 1;
+
+// writes a single audio track of already encoded frames into a webm file,
+// the writer sizes every element once it is closed so nothing is patched afterwards
+pub(crate) struct WebmChunkWriter {
+    writer: WebmWriter<File>,
+    cluster_start: Option<u64>,
+}
+
+impl WebmChunkWriter {
+    pub fn create(
+        output_file: &Path,
+        codec_id: &str,
+        codec_private: Option<&[u8]>,
+        sample_rate: f64,
+        channels: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut writer = WebmWriter::new(File::create(output_file)?);
+
+        let mut track = vec![
+            MatroskaSpec::TrackNumber(TRACK_NUMBER),
+            MatroskaSpec::TrackUid(TRACK_NUMBER),
+            // audio
+            MatroskaSpec::TrackType(2),
+            MatroskaSpec::CodecId(codec_id.to_string()),
+        ];
+        if let Some(codec_private) = codec_private {
+            track.push(MatroskaSpec::CodecPrivate(codec_private.to_vec()));
+        }
+        track.push(MatroskaSpec::Audio(Master::Full(vec![
+            MatroskaSpec::SamplingFrequency(sample_rate),
+            MatroskaSpec::Channels(channels),
+        ])));
+
+        let header = [
+            MatroskaSpec::Ebml(Master::Full(vec![
+                MatroskaSpec::EbmlVersion(1),
+                MatroskaSpec::EbmlReadVersion(1),
+                MatroskaSpec::EbmlMaxIdLength(4),
+                MatroskaSpec::EbmlMaxSizeLength(8),
+                MatroskaSpec::DocType("webm".to_string()),
+                MatroskaSpec::DocTypeVersion(4),
+                MatroskaSpec::DocTypeReadVersion(2),
+            ])),
+            MatroskaSpec::Segment(Master::Start),
+            // timestamps in milliseconds
+            MatroskaSpec::Info(Master::Full(vec![
+                MatroskaSpec::TimestampScale(1_000_000),
+                MatroskaSpec::MuxingApp("ffmpeg-audio".to_string()),
+                MatroskaSpec::WritingApp("ffmpeg-audio".to_string()),
+            ])),
+            MatroskaSpec::Tracks(Master::Full(vec![MatroskaSpec::TrackEntry(Master::Full(
+                track,
+            ))])),
+        ];
+        for tag in &header {
+            writer.write(tag)?;
+        }
+
+        Ok(Self {
+            writer,
+            cluster_start: None,
+        })
+    }
+
+    // `time` is milliseconds from the start of the file
+    pub fn write_frame(&mut self, time: u64, frame: &[u8]) -> Result<(), Box<dyn Error>> {
+        let cluster_start = match self.cluster_start {
+            Some(start) if time < start + CLUSTER_SPAN_MS => start,
+            previous => {
+                if previous.is_some() {
+                    self.writer.write(&MatroskaSpec::Cluster(Master::End))?;
+                }
+                self.writer.write(&MatroskaSpec::Cluster(Master::Start))?;
+                self.writer.write(&MatroskaSpec::Timestamp(time))?;
+                self.cluster_start = Some(time);
+                time
+            }
+        };
+
+        // track number as a one byte vint, the offset, then the keyframe flag
+        let mut block = Vec::with_capacity(frame.len() + 4);
+        block.push(0x80 | TRACK_NUMBER as u8);
+        block.extend_from_slice(&((time - cluster_start) as i16).to_be_bytes());
+        block.push(0x80);
+        block.extend_from_slice(frame);
+        self.writer.write(&MatroskaSpec::SimpleBlock(block))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        if self.cluster_start.is_some() {
+            self.writer.write(&MatroskaSpec::Cluster(Master::End))?;
+        }
+        self.writer.write(&MatroskaSpec::Segment(Master::End))?;
+        Ok(())
+    }
+}
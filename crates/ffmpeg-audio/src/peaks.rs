@@ -0,0 +1,170 @@
+use crate::{BackendError, MonoDecoder};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeaksConfig {
+    // rate the audio is decoded at before peaks are taken
+    pub sample_rate: u32,
+    // samples folded into one min/max pair, sample_rate / samples_per_pixel pairs per second
+    pub samples_per_pixel: u32,
+    // 8 or 16, the range of the stored values
+    pub bits: u8,
+}
+
+impl PeaksConfig {
+    // tells waveforms of the same audio apart, e.g. in cache file names
+    pub fn key(&self) -> String {
+        format!(
+            "{}-{}-{}",
+            self.sample_rate, self.samples_per_pixel, self.bits
+        )
+    }
+}
+
+impl Default for PeaksConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 8000,
+            samples_per_pixel: 80,
+            bits: 8,
+        }
+    }
+}
+
+// min/max pairs of a mono downmix, laid out like audiowaveform's output
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waveform {
+    pub sample_rate: u32,
+    pub samples_per_pixel: u32,
+    pub bits: u8,
+    pub data: Vec<(i16, i16)>,
+}
+
+impl Waveform {
+    // audiowaveform's version 2 JSON format
+    pub fn to_json(&self) -> String {
+        let data = self
+            .data
+            .iter()
+            .map(|(min, max)| format!("{},{}", min, max))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"version":2,"channels":1,"sample_rate":{},"samples_per_pixel":{},"bits":{},"length":{},"data":[{}]}}"#,
+            self.sample_rate,
+            self.samples_per_pixel,
+            self.bits,
+            self.data.len(),
+            data
+        )
+    }
+}
+
+// folds samples into min/max pairs scaled to `bits`
+struct PeakAccumulator {
+    samples_per_pixel: usize,
+    scale: f32,
+    count: usize,
+    min: f32,
+    max: f32,
+    data: Vec<(i16, i16)>,
+}
+
+impl PeakAccumulator {
+    fn new(samples_per_pixel: u32, bits: u8) -> Self {
+        let scale = if bits == 8 { 127.0 } else { 32767.0 };
+        Self {
+            samples_per_pixel: samples_per_pixel.max(1) as usize,
+            scale,
+            count: 0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            data: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.min = self.min.min(sample);
+            self.max = self.max.max(sample);
+            self.count += 1;
+            if self.count == self.samples_per_pixel {
+                self.flush();
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.count == 0 {
+            return;
+        }
+        let scale = |value: f32| (value.clamp(-1.0, 1.0) * self.scale).round() as i16;
+        self.data.push((scale(self.min), scale(self.max)));
+        self.count = 0;
+        self.min = f32::INFINITY;
+        self.max = f32::NEG_INFINITY;
+    }
+
+    fn finish(mut self) -> Vec<(i16, i16)> {
+        self.flush();
+        self.data
+    }
+}
+
+pub fn generate_peaks(input_file: &Path, config: &PeaksConfig) -> Result<Waveform, BackendError> {
+    let mut decoder = MonoDecoder::open(input_file, config.sample_rate)?;
+    let mut accumulator = PeakAccumulator::new(config.samples_per_pixel, config.bits);
+    decoder.read(0.0, f64::INFINITY, |_, batch| {
+        accumulator.push(batch);
+        true
+    })?;
+
+    Ok(Waveform {
+        sample_rate: config.sample_rate,
+        samples_per_pixel: config.samples_per_pixel,
+        bits: config.bits,
+        data: accumulator.finish(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peak_accumulator_works() {
+        let mut accumulator = PeakAccumulator::new(4, 8);
+        accumulator.push(&[0.5, -0.25, 1.0]);
+        accumulator.push(&[0.0, -2.0, 0.1]);
+        assert_eq!(accumulator.finish(), vec![(-32, 127), (-127, 13)]);
+
+        // a block that stays on one side of zero keeps its own extremes
+        let mut accumulator = PeakAccumulator::new(2, 8);
+        accumulator.push(&[0.5, 0.25, -0.5, -0.75]);
+        assert_eq!(accumulator.finish(), vec![(32, 64), (-95, -64)]);
+        assert!(PeakAccumulator::new(2, 8).finish().is_empty());
+    }
+
+    #[test]
+    fn to_json_works() {
+        let waveform = Waveform {
+            sample_rate: 8000,
+            samples_per_pixel: 80,
+            bits: 8,
+            data: vec![(-3, 5), (0, 1)],
+        };
+        assert_eq!(
+            waveform.to_json(),
+            r#"{"version":2,"channels":1,"sample_rate":8000,"samples_per_pixel":80,"bits":8,"length":2,"data":[-3,5,0,1]}"#
+        );
+    }
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn generate_peaks_works() {
+        let config = PeaksConfig::default();
+        let waveform = generate_peaks(Path::new("./sample.m4a"), &config).unwrap();
+        assert!(!waveform.data.is_empty());
+        assert!(waveform.data.iter().all(|(min, max)| min <= max));
+    }
+}
@@ -0,0 +1,66 @@
+// cleanup applied to the decoded audio before it is encoded for upload,
+// every stage is off by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Preprocess {
+    // mix all channels down first so the later stages run on one channel
+    pub downmix: bool,
+    // cut rumble and hum below this frequency (Hz)
+    pub highpass: Option<u32>,
+    // fft based broadband noise reduction
+    pub denoise: bool,
+    // EBU R128 loudness normalization, lifts quiet recordings
+    pub loudnorm: bool,
+}
+
+impl Preprocess {
+    // the filter chain for `Transcoder::with_filter`, none when nothing is enabled
+    pub fn filter_spec(&self) -> Option<String> {
+        let mut filters = Vec::new();
+        if self.downmix {
+            filters.push("aformat=channel_layouts=mono".to_string());
+        }
+        if let Some(frequency) = self.highpass.filter(|&hz| hz > 0) {
+            filters.push(format!("highpass=f={}", frequency));
+        }
+        if self.denoise {
+            filters.push("afftdn=nf=-25".to_string());
+        }
+        if self.loudnorm {
+            filters.push("loudnorm=I=-16:TP=-1.5:LRA=11".to_string());
+        }
+        if filters.is_empty() {
+            None
+        } else {
+            Some(filters.join(","))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_spec_works() {
+        assert_eq!(Preprocess::default().filter_spec(), None);
+        let preprocess = Preprocess {
+            downmix: true,
+            highpass: Some(80),
+            denoise: true,
+            loudnorm: true,
+        };
+        assert_eq!(
+            preprocess.filter_spec().unwrap(),
+            "aformat=channel_layouts=mono,highpass=f=80,afftdn=nf=-25,loudnorm=I=-16:TP=-1.5:LRA=11"
+        );
+        let preprocess = Preprocess {
+            highpass: Some(0),
+            loudnorm: true,
+            ..Preprocess::default()
+        };
+        assert_eq!(
+            preprocess.filter_spec().unwrap(),
+            "loudnorm=I=-16:TP=-1.5:LRA=11"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+#[cfg(feature = "ffmpeg")]
+use ffmpeg_next::{self as ffmpeg, codec, media};
+use std::collections::HashMap;
+#[cfg(feature = "ffmpeg")]
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    Audio,
+    Video,
+    Subtitle,
+    Other,
+}
+
+#[cfg(feature = "ffmpeg")]
+impl From<media::Type> for StreamKind {
+    fn from(medium: media::Type) -> Self {
+        match medium {
+            media::Type::Audio => Self::Audio,
+            media::Type::Video => Self::Video,
+            media::Type::Subtitle => Self::Subtitle,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub index: usize,
+    pub kind: StreamKind,
+    pub codec: String,
+    // seconds, none when the container does not record it per stream
+    pub duration: Option<f64>,
+    // bits per second, 0 when unknown
+    pub bit_rate: usize,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub language: Option<String>,
+    pub tags: HashMap<String, String>,
+}
+
+// what is actually inside a media file, as opposed to what the source claims
+#[derive(Debug, Clone)]
+pub struct MediaInfo {
+    // short name of the demuxer, e.g. `matroska,webm` or `mov,mp4,m4a,3gp,3g2,mj2`
+    pub container: String,
+    pub duration: f64,
+    pub size: u64,
+    pub bit_rate: i64,
+    pub streams: Vec<StreamInfo>,
+    pub tags: HashMap<String, String>,
+    pub(crate) best_audio: Option<usize>,
+}
+
+impl MediaInfo {
+    // the stream ffmpeg would pick for decoding audio
+    pub fn audio(&self) -> Option<&StreamInfo> {
+        let index = self.best_audio?;
+        self.streams.iter().find(|stream| stream.index == index)
+    }
+
+    pub fn streams_of(&self, kind: StreamKind) -> impl Iterator<Item = &StreamInfo> {
+        self.streams
+            .iter()
+            .filter(move |stream| stream.kind == kind)
+    }
+}
+
+#[cfg(feature = "ffmpeg")]
+fn collect_tags(metadata: ffmpeg::DictionaryRef) -> HashMap<String, String> {
+    metadata
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(feature = "ffmpeg")]
+fn stream_info(stream: &ffmpeg::Stream) -> StreamInfo {
+    let parameters = stream.parameters();
+    let kind = StreamKind::from(parameters.medium());
+    let tags = collect_tags(stream.metadata());
+    let duration = match stream.duration() {
+        ffmpeg::ffi::AV_NOPTS_VALUE => None,
+        duration => Some(duration as f64 * f64::from(stream.time_base())),
+    };
+
+    let mut info = StreamInfo {
+        index: stream.index(),
+        kind,
+        codec: parameters.id().name().to_string(),
+        duration,
+        bit_rate: 0,
+        sample_rate: None,
+        channels: None,
+        width: None,
+        height: None,
+        language: tags.get("language").cloned(),
+        tags,
+    };
+
+    let decoder = codec::context::Context::from_parameters(parameters).map(|c| c.decoder());
+    match (kind, decoder) {
+        (StreamKind::Audio, Ok(decoder)) => {
+            if let Ok(audio) = decoder.audio() {
+                info.bit_rate = audio.bit_rate();
+                info.sample_rate = Some(audio.rate());
+                info.channels = Some(audio.channels());
+            }
+        }
+        (StreamKind::Video, Ok(decoder)) => {
+            if let Ok(video) = decoder.video() {
+                info.bit_rate = video.bit_rate();
+                info.width = Some(video.width());
+                info.height = Some(video.height());
+            }
+        }
+        _ => {}
+    }
+    info
+}
+
+#[cfg(feature = "ffmpeg")]
+pub fn probe(path: &Path) -> Result<MediaInfo, ffmpeg::Error> {
+    ffmpeg::init()?;
+    let input_ctx = ffmpeg::format::input(path)?;
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    Ok(MediaInfo {
+        container: input_ctx.format().name().to_string(),
+        duration: input_ctx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE),
+        size,
+        bit_rate: input_ctx.bit_rate(),
+        streams: input_ctx.streams().map(|s| stream_info(&s)).collect(),
+        tags: collect_tags(input_ctx.metadata()),
+        best_audio: input_ctx
+            .streams()
+            .best(media::Type::Audio)
+            .map(|s| s.index()),
+    })
+}
+
+#[cfg(all(test, feature = "ffmpeg"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn probe_works() {
+        let info = probe(Path::new("./sample.m4a")).unwrap();
+        assert!(info.container.contains("m4a"));
+        assert!(info.duration > 0.0);
+        assert!(info.size > 0);
+
+        let audio = info.audio().unwrap();
+        assert_eq!(audio.kind, StreamKind::Audio);
+        assert_eq!(audio.codec, "aac");
+        assert!(audio.sample_rate.unwrap() > 0);
+        assert!(audio.channels.unwrap() > 0);
+        assert_eq!(info.streams_of(StreamKind::Audio).count(), 1);
+    }
+}
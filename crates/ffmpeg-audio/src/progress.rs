@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// shared with a running split, `cancel` stops it at the next packet or cut
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// turns input times into whole percents for a split's callback, only calling it
+// when the value moves
+pub(crate) struct Progress<'a> {
+    cancel: &'a CancelToken,
+    on_progress: &'a mut dyn FnMut(f64),
+    total_duration: f64,
+    last_percent: f64,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(
+        cancel: &'a CancelToken,
+        on_progress: &'a mut dyn FnMut(f64),
+        total_duration: f64,
+    ) -> Self {
+        Self {
+            cancel,
+            on_progress,
+            total_duration,
+            last_percent: -1.0,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    // `time` seconds of the input are done
+    pub fn report(&mut self, time: f64) {
+        if self.total_duration <= 0.0 {
+            return;
+        }
+        let percent = (time / self.total_duration * 100.0)
+            .clamp(0.0, 100.0)
+            .floor();
+        if percent > self.last_percent {
+            self.last_percent = percent;
+            (self.on_progress)(percent);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if self.last_percent < 100.0 {
+            self.last_percent = 100.0;
+            (self.on_progress)(100.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_works() {
+        let cancel = CancelToken::new();
+        let mut reported = Vec::new();
+        let mut on_progress = |percent| reported.push(percent);
+        let mut progress = Progress::new(&cancel, &mut on_progress, 200.0);
+        progress.report(0.0);
+        progress.report(0.5);
+        progress.report(50.0);
+        progress.report(40.0);
+        progress.report(500.0);
+        progress.finish();
+        assert_eq!(reported, vec![0.0, 25.0, 100.0]);
+
+        let shared = cancel.clone();
+        assert!(!cancel.is_cancelled());
+        shared.cancel();
+        assert!(cancel.is_cancelled());
+    }
+}
@@ -0,0 +1,122 @@
+// sample rate used for level analysis, speech energy sits well below 4 kHz
+pub(crate) const ANALYSIS_RATE: u32 = 8000;
+const LEVEL_WINDOW: f64 = 0.02;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SilenceConfig {
+    // how far (seconds) a cut may move away from the target boundary
+    pub tolerance: f64,
+    // windows quieter than this (dBFS) count as silence
+    pub threshold_db: f32,
+}
+
+impl Default for SilenceConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 5.0,
+            threshold_db: -40.0,
+        }
+    }
+}
+
+pub(crate) fn rms_db(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    10.0 * power.max(1e-12).log10()
+}
+
+// rms level in dBFS for each `window` seconds of samples
+pub(crate) fn levels(samples: &[f32], sample_rate: u32, window: f64) -> Vec<f32> {
+    let size = ((sample_rate as f64 * window) as usize).max(1);
+    samples.chunks(size).map(rms_db).collect()
+}
+
+// pick the silent window closest to `target`, or the quietest one if none is silent,
+// returns the time of the middle of that window
+pub(crate) fn nearest_silence(
+    levels: &[f32],
+    window: f64,
+    offset: f64,
+    target: f64,
+    threshold_db: f32,
+) -> Option<f64> {
+    let center = |i: usize| offset + (i as f64 + 0.5) * window;
+    let silent = levels
+        .iter()
+        .enumerate()
+        .filter(|(_, &level)| level < threshold_db)
+        .min_by(|(a, _), (b, _)| {
+            (center(*a) - target)
+                .abs()
+                .total_cmp(&(center(*b) - target).abs())
+        });
+    let (index, _) = match silent {
+        Some(found) => found,
+        None => levels.iter().enumerate().min_by(|(a, la), (b, lb)| {
+            la.total_cmp(lb).then(
+                (center(*a) - target)
+                    .abs()
+                    .total_cmp(&(center(*b) - target).abs()),
+            )
+        })?,
+    };
+    Some(center(index))
+}
+
+// move each target boundary to the nearest silence within the tolerance window,
+// `read_range` returns the samples at `ANALYSIS_RATE` between two times and the
+// time of the first one
+pub(crate) fn snap_to_silence<E>(
+    mut read_range: impl FnMut(f64, f64) -> Result<(f64, Vec<f32>), E>,
+    targets: &[f64],
+    config: &SilenceConfig,
+) -> Result<Vec<f64>, E> {
+    let mut cuts = Vec::with_capacity(targets.len());
+    for &target in targets {
+        let start = (target - config.tolerance).max(0.0);
+        let (first_time, samples) = read_range(start, target + config.tolerance)?;
+        let levels = levels(&samples, ANALYSIS_RATE, LEVEL_WINDOW);
+        let cut = nearest_silence(
+            &levels,
+            LEVEL_WINDOW,
+            first_time,
+            target,
+            config.threshold_db,
+        )
+        .unwrap_or(target);
+        cuts.push(cut);
+    }
+    Ok(cuts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rms_db_works() {
+        assert!(rms_db(&[0.0; 100]) < -100.0);
+        assert!((rms_db(&[1.0; 100])).abs() < 1e-4);
+        assert!((rms_db(&[0.1; 100]) + 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn nearest_silence_prefers_closest_silent_window() {
+        // 1 second windows starting at 10s, silent at 11s and 15s
+        let levels = vec![-10.0, -60.0, -10.0, -10.0, -10.0, -60.0, -10.0];
+        let cut = nearest_silence(&levels, 1.0, 10.0, 14.2, -40.0).unwrap();
+        assert_eq!(cut, 15.5);
+        let cut = nearest_silence(&levels, 1.0, 10.0, 12.0, -40.0).unwrap();
+        assert_eq!(cut, 11.5);
+    }
+
+    #[test]
+    fn nearest_silence_falls_back_to_quietest_window() {
+        let levels = vec![-10.0, -20.0, -30.0, -20.0];
+        let cut = nearest_silence(&levels, 1.0, 0.0, 0.0, -40.0).unwrap();
+        assert_eq!(cut, 2.5);
+        assert_eq!(nearest_silence(&[], 1.0, 0.0, 0.0, -40.0), None);
+    }
+}
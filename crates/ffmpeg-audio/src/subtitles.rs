@@ -0,0 +1,166 @@
+#[cfg(feature = "ffmpeg")]
+use ffmpeg_next::{self as ffmpeg, codec, codec::subtitle::Rect, media};
+#[cfg(feature = "ffmpeg")]
+use std::path::Path;
+
+// a text subtitle stream of a media file
+#[derive(Debug, Clone)]
+pub struct SubtitleTrack {
+    pub index: usize,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+// one cue in seconds, the same shape as a transcript segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleCue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+// bitmap formats like PGS and DVB would need OCR, only text ones are supported
+#[cfg(feature = "ffmpeg")]
+fn is_text_codec(id: codec::Id) -> bool {
+    matches!(
+        id,
+        codec::Id::SUBRIP
+            | codec::Id::SRT
+            | codec::Id::ASS
+            | codec::Id::SSA
+            | codec::Id::MOV_TEXT
+            | codec::Id::WEBVTT
+            | codec::Id::TEXT
+    )
+}
+
+// decoded text subtitles come out as ASS events,
+// `ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text`
+// or `Dialogue: Layer,Start,End,Style,Name,MarginL,MarginR,MarginV,Effect,Text` from older ffmpeg
+#[cfg(feature = "ffmpeg")]
+pub(crate) fn ass_dialogue_text(event: &str) -> String {
+    let raw = match event.strip_prefix("Dialogue:") {
+        Some(rest) => rest.splitn(10, ',').nth(9),
+        None => event.splitn(9, ',').nth(8),
+    }
+    .unwrap_or(event);
+
+    let mut text = String::new();
+    let mut in_override = false;
+    for c in raw.chars() {
+        match c {
+            '{' => in_override = true,
+            '}' if in_override => in_override = false,
+            _ if !in_override => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("\\N", " ")
+        .replace("\\n", " ")
+        .replace("\\h", " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(feature = "ffmpeg")]
+pub fn list_subtitle_tracks(input_file: &Path) -> Result<Vec<SubtitleTrack>, ffmpeg::Error> {
+    ffmpeg::init()?;
+    let input_ctx = ffmpeg::format::input(input_file)?;
+    Ok(input_ctx
+        .streams()
+        .filter(|stream| {
+            let parameters = stream.parameters();
+            parameters.medium() == media::Type::Subtitle && is_text_codec(parameters.id())
+        })
+        .map(|stream| {
+            let metadata = stream.metadata();
+            SubtitleTrack {
+                index: stream.index(),
+                codec: stream.parameters().id().name().to_string(),
+                language: metadata.get("language").map(String::from),
+                title: metadata.get("title").map(String::from),
+            }
+        })
+        .collect())
+}
+
+// decode every cue of the subtitle stream `stream_index`, sorted by start time
+#[cfg(feature = "ffmpeg")]
+pub fn extract_subtitles(
+    input_file: &Path,
+    stream_index: usize,
+) -> Result<Vec<SubtitleCue>, ffmpeg::Error> {
+    ffmpeg::init()?;
+    let mut input_ctx = ffmpeg::format::input(input_file)?;
+    let stream = input_ctx
+        .stream(stream_index)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    if !is_text_codec(stream.parameters().id()) {
+        return Err(ffmpeg::Error::DecoderNotFound);
+    }
+    let time_base = f64::from(stream.time_base());
+    let mut decoder = codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .subtitle()?;
+
+    let mut cues = Vec::new();
+    for (stream, packet) in input_ctx.packets() {
+        if stream.index() != stream_index {
+            continue;
+        }
+        let mut subtitle = ffmpeg::Subtitle::new();
+        if !decoder.decode(&packet, &mut subtitle).unwrap_or(false) {
+            continue;
+        }
+
+        let text = subtitle
+            .rects()
+            .filter_map(|rect| match rect {
+                Rect::Text(text) => Some(text.get().trim().to_string()),
+                Rect::Ass(ass) => Some(ass_dialogue_text(ass.get())),
+                _ => None,
+            })
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            continue;
+        }
+
+        let packet_start = packet.pts().unwrap_or(0) as f64 * time_base;
+        let start = packet_start + f64::from(subtitle.start()) / 1000.0;
+        let end = if packet.duration() > 0 {
+            packet_start + packet.duration() as f64 * time_base
+        } else {
+            packet_start + f64::from(subtitle.end()) / 1000.0
+        };
+        cues.push(SubtitleCue {
+            start,
+            end: end.max(start),
+            text,
+        });
+    }
+
+    cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(cues)
+}
+
+#[cfg(all(test, feature = "ffmpeg"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ass_dialogue_text_works() {
+        assert_eq!(
+            ass_dialogue_text("0,0,Default,,0,0,0,,{\\i1}Hello{\\i0}\\Nworld"),
+            "Hello world"
+        );
+        assert_eq!(
+            ass_dialogue_text("Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,a, b and c"),
+            "a, b and c"
+        );
+        assert_eq!(ass_dialogue_text("3,0,Default,,0,0,0,,"), "");
+    }
+}
@@ -0,0 +1,363 @@
+#[cfg(feature = "ffmpeg")]
+use crate::decode::build_graph;
+use crate::BackendError;
+#[cfg(feature = "ffmpeg")]
+use ffmpeg_next::{self as ffmpeg, codec, filter, format, frame, ChannelLayout, Rational, Rescale};
+use std::path::Path;
+
+// whisper resamples everything to 16 kHz mono, anything above that is wasted upload
+pub const SPEECH_SAMPLE_RATE: u32 = 16000;
+const DEFAULT_BITRATE: usize = 32_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechCodec {
+    Flac,
+    Opus,
+    Mp3,
+}
+
+impl SpeechCodec {
+    // accepts the names used in the settings, e.g. `opus` or `mp3`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "flac" => Some(Self::Flac),
+            "opus" | "ogg" => Some(Self::Opus),
+            "mp3" => Some(Self::Mp3),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Flac => "flac",
+            Self::Opus => "ogg",
+            Self::Mp3 => "mp3",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Flac => "audio/flac",
+            Self::Opus => "audio/ogg",
+            Self::Mp3 => "audio/mpeg",
+        }
+    }
+
+    #[cfg(feature = "ffmpeg")]
+    fn encoder(&self) -> Option<ffmpeg::Codec> {
+        match self {
+            Self::Flac => ffmpeg::encoder::find(codec::Id::FLAC),
+            // the native opus encoder is experimental and only takes 48 kHz
+            Self::Opus => ffmpeg::encoder::find_by_name("libopus"),
+            Self::Mp3 => ffmpeg::encoder::find_by_name("libmp3lame")
+                .or_else(|| ffmpeg::encoder::find(codec::Id::MP3)),
+        }
+    }
+}
+
+// the content type to upload an audio file with, judged by its extension
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("flac") => "audio/flac",
+        Some("ogg") | Some("opus") | Some("oga") => "audio/ogg",
+        Some("mp3") | Some("mpga") | Some("mpeg") => "audio/mpeg",
+        Some("m4a") | Some("mp4") => "audio/mp4",
+        Some("webm") => "audio/webm",
+        Some("wav") => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
+// `atempo` filters that play the audio `factor` times faster, older ffmpeg builds
+// only take factors between 0.5 and 2.0 so larger ones are chained
+pub fn tempo_filter(factor: f64) -> Option<String> {
+    if factor.is_nan() || factor <= 0.0 || (factor - 1.0).abs() < 1e-3 {
+        return None;
+    }
+    let mut stages = Vec::new();
+    let mut remaining = factor;
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    stages.push(format!("atempo={}", remaining));
+    Some(stages.join(","))
+}
+
+// `transcode` failed only because this build has no encoder for the codec, the
+// one error worth uploading the input as it is for
+#[cfg(feature = "ffmpeg")]
+pub fn is_missing_encoder(error: &BackendError) -> bool {
+    matches!(error, ffmpeg::Error::EncoderNotFound)
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+pub fn is_missing_encoder(_error: &BackendError) -> bool {
+    true
+}
+
+// only the ffmpeg backend can encode, the config is kept so callers build either way
+#[cfg_attr(not(feature = "ffmpeg"), allow(dead_code))]
+pub struct Transcoder {
+    codec: SpeechCodec,
+    bitrate: usize,
+    sample_rate: u32,
+    filters: Vec<String>,
+}
+
+impl Transcoder {
+    pub fn new(codec: SpeechCodec) -> Self {
+        Self {
+            codec,
+            bitrate: DEFAULT_BITRATE,
+            sample_rate: SPEECH_SAMPLE_RATE,
+            filters: Vec::new(),
+        }
+    }
+
+    // target bitrate in bits per second, ignored by flac
+    pub fn with_bitrate(mut self, bitrate: usize) -> Self {
+        self.bitrate = bitrate;
+        self
+    }
+
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    // an ffmpeg filter chain applied to the decoded audio before resampling,
+    // chains added later run after the earlier ones
+    pub fn with_filter(mut self, spec: impl Into<String>) -> Self {
+        self.filters.push(spec.into());
+        self
+    }
+
+    // speed the audio up by `factor`, timestamps of the output are `factor` times smaller
+    pub fn with_tempo(self, factor: f64) -> Self {
+        match tempo_filter(factor) {
+            Some(spec) => self.with_filter(spec),
+            None => self,
+        }
+    }
+
+    pub fn codec(&self) -> SpeechCodec {
+        self.codec
+    }
+
+    // no pure Rust encoders, callers fall back to the input as it is
+    #[cfg(not(feature = "ffmpeg"))]
+    pub fn transcode(&self, _input_file: &Path, _output_file: &Path) -> Result<(), BackendError> {
+        Err("encoding needs the ffmpeg feature".into())
+    }
+
+    // decode the best audio stream of `input_file`, downmix it to mono at the
+    // target rate and encode it into `output_file`, whose container is picked
+    // from its extension
+    #[cfg(feature = "ffmpeg")]
+    pub fn transcode(&self, input_file: &Path, output_file: &Path) -> Result<(), ffmpeg::Error> {
+        ffmpeg::init()?;
+        if let Some(parent) = output_file.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| ffmpeg::Error::Other { errno: -1 })?;
+        }
+
+        let mut input_ctx = format::input(input_file)?;
+        let input_stream = input_ctx
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let stream_index = input_stream.index();
+        let input_time_base = input_stream.time_base();
+        let mut decoder = codec::context::Context::from_parameters(input_stream.parameters())?
+            .decoder()
+            .audio()?;
+
+        let encoder_codec = self
+            .codec
+            .encoder()
+            .ok_or(ffmpeg::Error::EncoderNotFound)?
+            .audio()?;
+        let sample_format = encoder_codec
+            .formats()
+            .and_then(|mut formats| formats.next())
+            .ok_or(ffmpeg::Error::InvalidData)?;
+
+        let mut output_ctx = format::output(output_file)?;
+        let global_header = output_ctx
+            .format()
+            .flags()
+            .contains(format::flag::Flags::GLOBAL_HEADER);
+
+        let mut output_stream = output_ctx.add_stream(encoder_codec)?;
+        let mut encoder = codec::context::Context::from_parameters(output_stream.parameters())?
+            .encoder()
+            .audio()?;
+        if global_header {
+            encoder.set_flags(codec::flag::Flags::GLOBAL_HEADER);
+        }
+        encoder.set_rate(self.sample_rate as i32);
+        encoder.set_channel_layout(ChannelLayout::MONO);
+        encoder.set_format(sample_format);
+        encoder.set_bit_rate(self.bitrate);
+        encoder.set_time_base((1, self.sample_rate as i32));
+        output_stream.set_time_base((1, self.sample_rate as i32));
+
+        let mut encoder = encoder.open_as(encoder_codec)?;
+        output_stream.set_parameters(&encoder);
+
+        let mut spec = format!(
+            "aresample={rate},aformat=sample_fmts={}:channel_layouts=mono:sample_rates={rate}",
+            sample_format.name(),
+            rate = self.sample_rate
+        );
+        if !self.filters.is_empty() {
+            spec = format!("{},{}", self.filters.join(","), spec);
+        }
+        let mut graph = build_graph(&decoder, input_time_base, &spec)?;
+        if !encoder_codec
+            .capabilities()
+            .contains(codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE)
+        {
+            graph
+                .get("out")
+                .unwrap()
+                .sink()
+                .set_frame_size(encoder.frame_size());
+        }
+        let sink_time_base = graph.get("out").unwrap().sink().time_base();
+
+        output_ctx.write_header()?;
+        let output_time_base = output_ctx.stream(0).unwrap().time_base();
+
+        let time_bases = (sink_time_base, encoder.time_base(), output_time_base);
+        let mut decoded = frame::Audio::empty();
+        for (stream, packet) in input_ctx.packets() {
+            if stream.index() != stream_index || decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                let timestamp = decoded.timestamp();
+                decoded.set_pts(timestamp);
+                graph.get("in").unwrap().source().add(&decoded)?;
+                encode_filtered(&mut graph, &mut encoder, &mut output_ctx, time_bases)?;
+            }
+        }
+
+        decoder.send_eof()?;
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            let timestamp = decoded.timestamp();
+            decoded.set_pts(timestamp);
+            graph.get("in").unwrap().source().add(&decoded)?;
+        }
+        graph.get("in").unwrap().source().flush()?;
+        encode_filtered(&mut graph, &mut encoder, &mut output_ctx, time_bases)?;
+        encoder.send_eof()?;
+        write_packets(&mut encoder, &mut output_ctx, time_bases.1, time_bases.2)?;
+
+        output_ctx.write_trailer()?;
+        Ok(())
+    }
+}
+
+// move every frame waiting in the graph sink through the encoder,
+// `time_bases` are those of the sink, the encoder and the output stream
+#[cfg(feature = "ffmpeg")]
+fn encode_filtered(
+    graph: &mut filter::Graph,
+    encoder: &mut codec::encoder::Audio,
+    output_ctx: &mut format::context::Output,
+    time_bases: (Rational, Rational, Rational),
+) -> Result<(), ffmpeg::Error> {
+    let (sink_time_base, encoder_time_base, output_time_base) = time_bases;
+    let mut filtered = frame::Audio::empty();
+    while graph
+        .get("out")
+        .unwrap()
+        .sink()
+        .frame(&mut filtered)
+        .is_ok()
+    {
+        let pts = filtered
+            .pts()
+            .map(|pts| pts.rescale(sink_time_base, encoder_time_base));
+        filtered.set_pts(pts);
+        encoder.send_frame(&filtered)?;
+        write_packets(encoder, output_ctx, encoder_time_base, output_time_base)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ffmpeg")]
+fn write_packets(
+    encoder: &mut codec::encoder::Audio,
+    output_ctx: &mut format::context::Output,
+    encoder_time_base: Rational,
+    output_time_base: Rational,
+) -> Result<(), ffmpeg::Error> {
+    let mut encoded = ffmpeg::Packet::empty();
+    while encoder.receive_packet(&mut encoded).is_ok() {
+        encoded.set_stream(0);
+        encoded.rescale_ts(encoder_time_base, output_time_base);
+        encoded.write_interleaved(output_ctx)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speech_codec_from_name_works() {
+        assert_eq!(SpeechCodec::from_name("Opus"), Some(SpeechCodec::Opus));
+        assert_eq!(SpeechCodec::from_name(" flac "), Some(SpeechCodec::Flac));
+        assert_eq!(SpeechCodec::from_name("mp3"), Some(SpeechCodec::Mp3));
+        assert_eq!(SpeechCodec::from_name("aac"), None);
+    }
+
+    #[cfg(feature = "ffmpeg")]
+    #[test]
+    #[ignore = "reads ./sample.m4a, a local recording that is not committed"]
+    fn transcode_works() {
+        let input_file = Path::new("./sample.m4a");
+        let output_file = Path::new("output_dir/sample.flac");
+        Transcoder::new(SpeechCodec::Flac)
+            .transcode(input_file, output_file)
+            .unwrap();
+
+        let context = format::input(&output_file).unwrap();
+        let stream = context.streams().best(ffmpeg::media::Type::Audio).unwrap();
+        let decoder = codec::context::Context::from_parameters(stream.parameters())
+            .unwrap()
+            .decoder()
+            .audio()
+            .unwrap();
+        assert_eq!(decoder.rate(), SPEECH_SAMPLE_RATE);
+        assert_eq!(decoder.channels(), 1);
+    }
+
+    #[test]
+    fn tempo_filter_works() {
+        assert_eq!(tempo_filter(1.0), None);
+        assert_eq!(tempo_filter(0.0), None);
+        assert_eq!(tempo_filter(1.5).unwrap(), "atempo=1.5");
+        assert_eq!(tempo_filter(3.0).unwrap(), "atempo=2.0,atempo=1.5");
+        assert_eq!(tempo_filter(0.25).unwrap(), "atempo=0.5,atempo=0.5");
+    }
+
+    #[test]
+    fn mime_type_works() {
+        assert_eq!(mime_type(Path::new("chunk_001.ogg")), "audio/ogg");
+        assert_eq!(mime_type(Path::new("temp.M4A")), "audio/mp4");
+        assert_eq!(mime_type(Path::new("temp.webm")), "audio/webm");
+        assert_eq!(mime_type(Path::new("temp")), "application/octet-stream");
+    }
+}
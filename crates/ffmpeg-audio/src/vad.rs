@@ -0,0 +1,439 @@
+use crate::silence::{rms_db, ANALYSIS_RATE};
+use crate::{BackendError, MonoDecoder};
+use std::path::Path;
+
+const VAD_WINDOW: f64 = 0.03;
+// speech and music are told apart over blocks this long, after Lu, Zhang and
+// Jiang: speech keeps dipping in energy between syllables and switches between
+// voiced sounds and fricatives with many zero crossings, music holds both steady
+const MUSIC_BLOCK: f64 = 1.0;
+// a block with fewer low energy or high zero crossing windows than this is music
+const MIN_LOW_ENERGY_RATIO: f32 = 0.1;
+const MIN_HIGH_ZCR_RATIO: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy)]
+pub struct VadConfig {
+    // windows louder than this (dBFS) count as speech
+    pub threshold_db: f32,
+    // shorter bursts are dropped as clicks and noise
+    pub min_speech: f64,
+    // shorter pauses are kept so sentences are not chopped apart
+    pub min_silence: f64,
+    // seconds kept around every region so word onsets survive
+    pub padding: f64,
+    // treat steady passages without speech, e.g. intro music, as silence
+    pub drop_music: bool,
+    // the shortest pauses are kept until no more regions than this are left,
+    // `speech_filter` evaluates one term per region for every frame
+    pub max_regions: usize,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            threshold_db: -40.0,
+            min_speech: 0.25,
+            min_silence: 1.0,
+            padding: 0.2,
+            drop_music: true,
+            max_regions: 200,
+        }
+    }
+}
+
+// a span of the input that contains speech, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeechRegion {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl SpeechRegion {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+fn zero_crossing_rate(samples: &[f32]) -> f32 {
+    let crossings = samples
+        .windows(2)
+        .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+        .count();
+    crossings as f32 / samples.len().max(1) as f32
+}
+
+// silence the windows of every block that sounds like music rather than
+// speech, `levels` in dB and `zero_crossings` per window
+pub(crate) fn suppress_music(levels: &mut [f32], zero_crossings: &[f32], window: f64) {
+    let block = ((MUSIC_BLOCK / window).round() as usize).max(1);
+    for (levels, zero_crossings) in levels.chunks_mut(block).zip(zero_crossings.chunks(block)) {
+        // a short tail says too little either way
+        if levels.len() < block / 2 {
+            continue;
+        }
+        let count = levels.len() as f32;
+        let energies: Vec<f32> = levels.iter().map(|db| 10f32.powf(db / 10.0)).collect();
+        let mean_energy = energies.iter().sum::<f32>() / count;
+        let low_energy = energies
+            .iter()
+            .filter(|&&energy| energy < 0.5 * mean_energy)
+            .count() as f32
+            / count;
+        let mean_zcr = zero_crossings.iter().sum::<f32>() / count;
+        let high_zcr = zero_crossings
+            .iter()
+            .filter(|&&zcr| zcr > 1.5 * mean_zcr)
+            .count() as f32
+            / count;
+        if low_energy < MIN_LOW_ENERGY_RATIO && high_zcr < MIN_HIGH_ZCR_RATIO {
+            levels.fill(f32::NEG_INFINITY);
+        }
+    }
+}
+
+// turn per window levels into speech regions, `offset` is the time of the first window
+pub(crate) fn speech_regions(
+    levels: &[f32],
+    window: f64,
+    offset: f64,
+    config: &VadConfig,
+) -> Vec<SpeechRegion> {
+    // runs of loud windows as (first, last) indices
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, &level) in levels.iter().enumerate() {
+        if level < config.threshold_db {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, last)) if (i - *last - 1) as f64 * window < config.min_silence.max(window) => {
+                *last = i
+            }
+            _ => runs.push((i, i)),
+        }
+    }
+    let regions = runs.into_iter().map(|(first, last)| SpeechRegion {
+        start: offset + first as f64 * window,
+        end: offset + (last + 1) as f64 * window,
+    });
+
+    let total_end = offset + levels.len() as f64 * window;
+    let mut padded: Vec<SpeechRegion> = Vec::new();
+    for region in regions.filter(|region| region.duration() >= config.min_speech) {
+        let start = (region.start - config.padding).max(offset);
+        let end = (region.end + config.padding).min(total_end);
+        match padded.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => padded.push(SpeechRegion { start, end }),
+        }
+    }
+    merge_closest(padded, config.max_regions)
+}
+
+// close the shortest gaps between regions until at most `max_regions` are left
+fn merge_closest(regions: Vec<SpeechRegion>, max_regions: usize) -> Vec<SpeechRegion> {
+    let max_regions = max_regions.max(1);
+    if regions.len() <= max_regions {
+        return regions;
+    }
+    let mut gaps: Vec<f64> = regions
+        .windows(2)
+        .map(|pair| pair[1].start - pair[0].end)
+        .collect();
+    gaps.sort_by(f64::total_cmp);
+    let to_close = regions.len() - max_regions;
+    let longest_closed = gaps[to_close - 1];
+    // gaps as long as the longest closed one are only closed while needed
+    let mut ties = to_close - gaps.partition_point(|&gap| gap < longest_closed);
+
+    let mut merged: Vec<SpeechRegion> = Vec::with_capacity(max_regions);
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start - last.end < longest_closed => last.end = region.end,
+            Some(last) if region.start - last.end == longest_closed && ties > 0 => {
+                ties -= 1;
+                last.end = region.end
+            }
+            _ => merged.push(region),
+        }
+    }
+    merged
+}
+
+// find the speech in the best audio stream of `input_file`
+pub fn detect_speech(
+    input_file: &Path,
+    config: &VadConfig,
+) -> Result<Vec<SpeechRegion>, BackendError> {
+    let mut decoder = MonoDecoder::open(input_file, ANALYSIS_RATE)?;
+    let size = (ANALYSIS_RATE as f64 * VAD_WINDOW) as usize;
+
+    let mut first_time = None;
+    let mut levels = Vec::new();
+    let mut zero_crossings = Vec::new();
+    let mut pending: Vec<f32> = Vec::with_capacity(size);
+    decoder.read(0.0, f64::INFINITY, |time, batch| {
+        first_time.get_or_insert(time);
+        for &sample in batch {
+            pending.push(sample);
+            if pending.len() == size {
+                levels.push(rms_db(&pending));
+                zero_crossings.push(zero_crossing_rate(&pending));
+                pending.clear();
+            }
+        }
+        true
+    })?;
+    if !pending.is_empty() {
+        levels.push(rms_db(&pending));
+        zero_crossings.push(zero_crossing_rate(&pending));
+    }
+    if config.drop_music {
+        suppress_music(&mut levels, &zero_crossings, VAD_WINDOW);
+    }
+
+    Ok(speech_regions(
+        &levels,
+        VAD_WINDOW,
+        first_time.unwrap_or(0.0),
+        config,
+    ))
+}
+
+// filter spec for `Transcoder::with_filter` that keeps only the regions and
+// closes the gaps, pair it with a `TimeMap` of the same regions; one term per
+// region, so keep them to `VadConfig::max_regions`
+pub fn speech_filter(regions: &[SpeechRegion]) -> String {
+    let expr = regions
+        .iter()
+        .map(|region| format!("between(t,{:.3},{:.3})", region.start, region.end))
+        .collect::<Vec<_>>()
+        .join("+");
+    format!("aselect='{}',asetpts=N/SR/TB", expr)
+}
+
+// maps times in audio built from only the speech regions, and possibly sped up,
+// back onto the input, the default map changes nothing
+#[derive(Debug, Clone)]
+pub struct TimeMap {
+    // (start in the condensed audio, the region it came from)
+    spans: Vec<(f64, SpeechRegion)>,
+    tempo: f64,
+}
+
+impl Default for TimeMap {
+    fn default() -> Self {
+        Self {
+            spans: Vec::new(),
+            tempo: 1.0,
+        }
+    }
+}
+
+impl TimeMap {
+    pub fn new(regions: &[SpeechRegion]) -> Self {
+        let mut condensed = 0.0;
+        let spans = regions
+            .iter()
+            .map(|region| {
+                let span = (condensed, *region);
+                condensed += region.duration();
+                span
+            })
+            .collect();
+        Self { spans, tempo: 1.0 }
+    }
+
+    // the condensed audio was played `factor` times faster, see `Transcoder::with_tempo`
+    pub fn with_tempo(mut self, factor: f64) -> Self {
+        if factor > 0.0 {
+            self.tempo = factor;
+        }
+        self
+    }
+
+    // what the map was built from, `TimeMap::new(&map.regions()).with_tempo(map.tempo())`
+    // gives it back
+    pub fn regions(&self) -> Vec<SpeechRegion> {
+        self.spans.iter().map(|(_, region)| *region).collect()
+    }
+
+    pub fn tempo(&self) -> f64 {
+        self.tempo
+    }
+
+    // length of the condensed audio in seconds
+    pub fn duration(&self) -> f64 {
+        self.spans
+            .last()
+            .map_or(0.0, |(start, region)| start + region.duration())
+    }
+
+    pub fn to_original(&self, time: f64) -> f64 {
+        let time = time * self.tempo;
+        if self.spans.is_empty() {
+            return time;
+        }
+        let index = self.spans.partition_point(|(start, _)| *start <= time);
+        match index.checked_sub(1).map(|i| self.spans[i]) {
+            Some((start, region)) => (region.start + time - start).min(region.end),
+            None => time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speech_regions_bridge_pauses_and_drop_clicks() {
+        let config = VadConfig {
+            threshold_db: -40.0,
+            min_speech: 0.25,
+            min_silence: 1.0,
+            padding: 0.0,
+            drop_music: false,
+            max_regions: 200,
+        };
+        // 0.1 second windows: speech 1.0-1.5 and 2.0-2.5, a click at 5.0, speech 8.0-9.0
+        let mut levels = vec![-60.0; 100];
+        levels[10..15].fill(-20.0);
+        levels[20..25].fill(-20.0);
+        levels[50] = -10.0;
+        levels[80..90].fill(-20.0);
+        let regions = speech_regions(&levels, 0.1, 0.0, &config);
+        assert_eq!(regions.len(), 2);
+        assert!((regions[0].start - 1.0).abs() < 1e-9);
+        assert!((regions[0].end - 2.5).abs() < 1e-9);
+        assert!((regions[1].start - 8.0).abs() < 1e-9);
+        assert!((regions[1].end - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn speech_regions_padding_merges_and_clamps() {
+        let config = VadConfig {
+            padding: 0.6,
+            min_silence: 0.0,
+            ..VadConfig::default()
+        };
+        let mut levels = vec![-60.0; 30];
+        levels[0..5].fill(-20.0);
+        levels[12..17].fill(-20.0);
+        levels[27..30].fill(-20.0);
+        let regions = speech_regions(&levels, 0.1, 0.0, &config);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].start, 0.0);
+        assert!((regions[0].end - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn suppress_music_keeps_speech() {
+        // 0.1 second windows: a steady tone for two seconds, then two seconds of
+        // syllables with pauses and fricatives
+        let mut levels = vec![-20.0; 40];
+        let mut zero_crossings = vec![0.05; 40];
+        for i in 20..40 {
+            if i % 3 == 0 {
+                levels[i] = -50.0;
+            }
+            if i % 4 == 0 {
+                zero_crossings[i] = 0.4;
+            }
+        }
+        suppress_music(&mut levels, &zero_crossings, 0.1);
+        assert!(levels[..20].iter().all(|level| level.is_infinite()));
+        assert!(levels[20..].iter().all(|level| level.is_finite()));
+
+        assert!((zero_crossing_rate(&[0.5, -0.5, 0.5, -0.5]) - 0.75).abs() < 1e-6);
+        assert_eq!(zero_crossing_rate(&[0.1, 0.2, 0.3]), 0.0);
+    }
+
+    #[test]
+    fn time_map_works() {
+        let regions = vec![
+            SpeechRegion {
+                start: 10.0,
+                end: 20.0,
+            },
+            SpeechRegion {
+                start: 50.0,
+                end: 55.0,
+            },
+        ];
+        let map = TimeMap::new(&regions);
+        assert_eq!(map.duration(), 15.0);
+        assert_eq!(map.to_original(0.0), 10.0);
+        assert_eq!(map.to_original(9.5), 19.5);
+        assert_eq!(map.to_original(10.0), 50.0);
+        assert_eq!(map.to_original(14.0), 54.0);
+        assert_eq!(map.to_original(20.0), 55.0);
+        assert_eq!(TimeMap::default().to_original(3.0), 3.0);
+
+        let map = TimeMap::new(&regions).with_tempo(2.0);
+        assert_eq!(map.to_original(2.0), 14.0);
+        assert_eq!(map.to_original(6.0), 52.0);
+        assert_eq!(map.regions(), regions);
+        assert_eq!(map.tempo(), 2.0);
+        assert_eq!(TimeMap::default().with_tempo(1.5).to_original(4.0), 6.0);
+    }
+
+    #[test]
+    fn speech_filter_works() {
+        let regions = vec![
+            SpeechRegion {
+                start: 1.0,
+                end: 2.5,
+            },
+            SpeechRegion {
+                start: 8.0,
+                end: 9.0,
+            },
+        ];
+        assert_eq!(
+            speech_filter(&regions),
+            "aselect='between(t,1.000,2.500)+between(t,8.000,9.000)',asetpts=N/SR/TB"
+        );
+    }
+
+    #[test]
+    fn many_regions_are_merged() {
+        let config = VadConfig {
+            min_speech: 0.0,
+            min_silence: 0.0,
+            padding: 0.0,
+            drop_music: false,
+            max_regions: 100,
+            ..VadConfig::default()
+        };
+        // three hours of 0.1 second windows, a word every second with a longer
+        // pause every minute
+        let mut levels = vec![-60.0; 108_000];
+        for second in 0..10_800 {
+            if second % 60 != 59 {
+                levels[second * 10..second * 10 + 5].fill(-20.0);
+            }
+        }
+        let regions = speech_regions(&levels, 0.1, 0.0, &config);
+        assert_eq!(regions.len(), 100);
+        assert!(regions.windows(2).all(|pair| pair[0].end < pair[1].start));
+        assert_eq!(regions[0].start, 0.0);
+        assert!((regions[99].end - 10_798.5).abs() < 1e-6);
+        // the minute pauses outlast the rest and are the gaps that stay
+        assert!(regions
+            .windows(2)
+            .all(|pair| pair[1].start - pair[0].end > 1.0));
+        assert_eq!(speech_filter(&regions).matches("between").count(), 100);
+
+        let unmerged = speech_regions(
+            &levels,
+            0.1,
+            0.0,
+            &VadConfig {
+                max_regions: usize::MAX,
+                ..config
+            },
+        );
+        assert_eq!(unmerged.len(), 10_620);
+        assert_eq!(merge_closest(unmerged, 1).len(), 1);
+    }
+}
@@ -0,0 +1,210 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{error::Error, fs, path::PathBuf};
+use tube_rs::{subtitle, CaptionItem, YoutubeAudio};
+
+#[derive(Parser)]
+#[command(
+    name = "tube-rs",
+    about = "Inspect and download youtube audio and captions"
+)]
+struct Cli {
+    /// https proxy, e.g. http://127.0.0.1:7890
+    #[arg(long, global = true)]
+    proxy: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the parsed audio info as JSON
+    Info { url: String },
+    /// List the adaptive formats
+    Formats { url: String },
+    /// List the caption tracks
+    Captions { url: String },
+    /// Download the lowest bitrate audio stream
+    DownloadAudio {
+        url: String,
+        /// output file, defaults to `<video_id>.webm` or `<video_id>.m4a`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Download a caption track
+    DownloadCaption {
+        url: String,
+        /// vss id of the track, e.g. `.en` or `a.en`, defaults to english or the first track
+        #[arg(short, long)]
+        lang: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = CaptionFormat::Srt)]
+        format: CaptionFormat,
+        /// output file, prints to stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CaptionFormat {
+    Srt,
+    Vtt,
+    Json,
+}
+
+// `.en` for uploaded captions, `a.en` for auto-generated ones
+fn caption_language(vss_id: &str) -> &str {
+    vss_id
+        .strip_prefix("a.")
+        .or_else(|| vss_id.strip_prefix('.'))
+        .unwrap_or(vss_id)
+}
+
+// the track for `lang`, english or else the first one by default, uploaded
+// captions before auto-generated ones
+fn select_caption<'a>(captions: &'a [CaptionItem], lang: Option<&str>) -> Option<&'a CaptionItem> {
+    let found = captions
+        .iter()
+        .filter(|item| {
+            let wanted = lang.unwrap_or("en");
+            item.vss_id == wanted || caption_language(&item.vss_id) == wanted
+        })
+        .min_by_key(|item| item.vss_id.starts_with("a."));
+    match lang {
+        Some(_) => found,
+        None => found.or(captions.first()),
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let youtube_audio = YoutubeAudio::new(cli.proxy.as_deref());
+
+    match cli.command {
+        Command::Info { url } => {
+            let audio_data = youtube_audio
+                .get_video_info(&url)
+                .await
+                .ok_or("failed to parse audio info")?;
+            println!("{}", serde_json::to_string_pretty(&audio_data)?);
+        }
+        Command::Formats { url } => {
+            let formats = youtube_audio
+                .get_formats(&url)
+                .await
+                .ok_or("failed to parse formats")?;
+            for format in formats {
+                println!(
+                    "{:>8}  {:>12}  {}",
+                    format.bitrate, format.content_length, format.mime_type
+                );
+            }
+        }
+        Command::Captions { url } => {
+            let captions = youtube_audio
+                .get_captions(&url)
+                .await
+                .ok_or("failed to parse captions")?;
+            if captions.is_empty() {
+                eprintln!("no captions found");
+            }
+            for caption in captions {
+                println!("{}\t{}", caption.vss_id, caption.base_url);
+            }
+        }
+        Command::DownloadAudio { url, output } => {
+            let audio_data = youtube_audio
+                .get_video_info(&url)
+                .await
+                .ok_or("failed to parse audio info")?;
+            let output = output.unwrap_or_else(|| {
+                let suffix = if audio_data.mime_type.contains("webm") {
+                    "webm"
+                } else {
+                    "m4a"
+                };
+                PathBuf::from(format!("{}.{}", audio_data.video_id, suffix))
+            });
+            youtube_audio
+                .download_audio(&audio_data.audio_url, audio_data.audio_filesize, &output)
+                .await?;
+            eprintln!(
+                "saved {} bytes to {}",
+                audio_data.audio_filesize,
+                output.display()
+            );
+        }
+        Command::DownloadCaption {
+            url,
+            lang,
+            format,
+            output,
+        } => {
+            let captions = youtube_audio
+                .get_captions(&url)
+                .await
+                .ok_or("failed to parse captions")?;
+            let caption =
+                select_caption(&captions, lang.as_deref()).ok_or("no matching caption track")?;
+            let subtitles = youtube_audio
+                .download_caption(&caption.base_url, &caption.vss_id)
+                .await?;
+            let content = match format {
+                CaptionFormat::Srt => subtitle::to_srt(&subtitles),
+                CaptionFormat::Vtt => subtitle::to_vtt(&subtitles),
+                CaptionFormat::Json => serde_json::to_string_pretty(&subtitles)?,
+            };
+            match output {
+                Some(path) => fs::write(path, content)?,
+                None => print!("{content}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caption_language_works() {
+        assert_eq!(caption_language(".en"), "en");
+        assert_eq!(caption_language("a.en"), "en");
+        assert_eq!(caption_language("a.ar"), "ar");
+        assert_eq!(caption_language(".af"), "af");
+        assert_eq!(caption_language("am"), "am");
+    }
+
+    #[test]
+    fn select_caption_works() {
+        let captions = [".en-GB", "a.en", ".fr", ".en"]
+            .map(|vss_id| CaptionItem {
+                base_url: format!("https://example.com/{vss_id}"),
+                vss_id: vss_id.to_string(),
+            })
+            .to_vec();
+        let vss_id = |lang| select_caption(&captions, lang).map(|item| item.vss_id.as_str());
+        assert_eq!(vss_id(None), Some(".en"));
+        assert_eq!(vss_id(Some("en")), Some(".en"));
+        assert_eq!(vss_id(Some("a.en")), Some("a.en"));
+        assert_eq!(vss_id(Some("en-GB")), Some(".en-GB"));
+        assert_eq!(vss_id(Some("de")), None);
+        assert_eq!(
+            select_caption(&captions[..1], None).unwrap().vss_id,
+            ".en-GB"
+        );
+        assert_eq!(
+            select_caption(&captions[1..2], None).unwrap().vss_id,
+            "a.en"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const AUTH_FILE: &str = "auth.json";
+
+// disk cache for the player js and the auth data scraped from it, so a batch
+// of videos only needs one watch page and one base.js fetch
+pub struct PlayerCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedAuth {
+    pub js_url: String,
+    pub visitor_data: String,
+    pub signature_timestamp: String,
+    fetched_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// turn "/s/player/abc/player_ias.vflset/en_US/base.js" into a flat file name
+fn player_file_name(js_url: &str) -> String {
+    let name: String = js_url
+        .trim_start_matches("https://www.youtube.com")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}.js", name.trim_matches('_'))
+}
+
+impl PlayerCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    fn is_fresh(&self, fetched_at: u64) -> bool {
+        now_secs().saturating_sub(fetched_at) < self.ttl.as_secs()
+    }
+
+    fn ensure_dir(&self) -> std::io::Result<()> {
+        if !self.dir.is_dir() {
+            fs::create_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_auth(&self) -> Option<CachedAuth> {
+        let contents = fs::read_to_string(self.dir.join(AUTH_FILE)).ok()?;
+        let auth: CachedAuth = serde_json::from_str(&contents).ok()?;
+        if self.is_fresh(auth.fetched_at) {
+            Some(auth)
+        } else {
+            None
+        }
+    }
+
+    pub fn store_auth(
+        &self,
+        js_url: &str,
+        visitor_data: &str,
+        signature_timestamp: &str,
+    ) -> std::io::Result<()> {
+        self.ensure_dir()?;
+        let auth = CachedAuth {
+            js_url: js_url.to_string(),
+            visitor_data: visitor_data.to_string(),
+            signature_timestamp: signature_timestamp.to_string(),
+            fetched_at: now_secs(),
+        };
+        let json = serde_json::to_string(&auth)?;
+        fs::write(self.dir.join(AUTH_FILE), json)
+    }
+
+    pub fn load_player_js(&self, js_url: &str) -> Option<String> {
+        let path = self.dir.join(player_file_name(js_url));
+        let modified = fs::metadata(&path).ok()?.modified().ok()?;
+        let fetched_at = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        if !self.is_fresh(fetched_at) {
+            return None;
+        }
+        fs::read_to_string(path).ok()
+    }
+
+    pub fn store_player_js(&self, js_url: &str, content: &str) -> std::io::Result<()> {
+        self.ensure_dir()?;
+        fs::write(self.dir.join(player_file_name(js_url)), content)
+    }
+
+    pub fn clear(&self) -> std::io::Result<()> {
+        if self.dir.is_dir() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, ttl: Duration) -> PlayerCache {
+        let dir = std::env::temp_dir().join(format!("tube-rs-cache-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        PlayerCache::new(dir, ttl)
+    }
+
+    #[test]
+    fn player_file_name_works() {
+        assert_eq!(
+            player_file_name("/s/player/3bb1f723/player_ias.vflset/en_US/base.js"),
+            "s_player_3bb1f723_player_ias_vflset_en_US_base_js.js"
+        );
+        assert_eq!(
+            player_file_name("https://www.youtube.com/s/player/3bb1f723/base.js"),
+            player_file_name("/s/player/3bb1f723/base.js")
+        );
+    }
+
+    #[test]
+    fn auth_roundtrip_works() {
+        let cache = temp_cache("auth", Duration::from_secs(60));
+        assert!(cache.load_auth().is_none());
+        cache
+            .store_auth("/s/player/abc/base.js", "visitor", "20000")
+            .unwrap();
+        let auth = cache.load_auth().unwrap();
+        assert_eq!(auth.js_url, "/s/player/abc/base.js");
+        assert_eq!(auth.visitor_data, "visitor");
+        assert_eq!(auth.signature_timestamp, "20000");
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn expired_entries_are_ignored() {
+        let cache = temp_cache("expired", Duration::ZERO);
+        cache.store_auth("/s/player/abc/base.js", "v", "1").unwrap();
+        cache
+            .store_player_js("/s/player/abc/base.js", "js")
+            .unwrap();
+        assert!(cache.load_auth().is_none());
+        assert!(cache.load_player_js("/s/player/abc/base.js").is_none());
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn player_js_roundtrip_works() {
+        let cache = temp_cache("player", Duration::from_secs(60));
+        assert!(cache.load_player_js("/s/player/abc/base.js").is_none());
+        cache
+            .store_player_js("/s/player/abc/base.js", "signatureTimestamp:20000")
+            .unwrap();
+        assert_eq!(
+            cache.load_player_js("/s/player/abc/base.js").as_deref(),
+            Some("signatureTimestamp:20000")
+        );
+        assert!(cache.load_player_js("/s/player/def/base.js").is_none());
+        cache.clear().unwrap();
+    }
+}
@@ -5,10 +5,23 @@ use reqwest::{
 };
 use serde::{Deserialize, Serialize};
 use serde_xml_rs::from_str;
-use std::{error::Error, fs::File, io::Write, path::Path, time::Duration};
+use std::{
+    error::Error,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+mod cache;
+pub mod subtitle;
+mod target;
+pub use cache::PlayerCache;
+pub use target::YoutubeTarget;
 
 pub struct YoutubeAudio {
     client: Client,
+    cache: Option<PlayerCache>,
 }
 
 #[derive(Serialize, Debug)]
@@ -108,21 +121,21 @@ struct PlayerCaptionsTracklistRenderer {
     caption_tracks: Vec<CaptionItem>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-struct CaptionItem {
-    base_url: String,
-    vss_id: String,
+pub struct CaptionItem {
+    pub base_url: String,
+    pub vss_id: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-struct Format {
-    mime_type: String,
-    bitrate: u32,
-    url: String,
-    content_length: String,
-    last_modified: String,
+pub struct Format {
+    pub mime_type: String,
+    pub bitrate: u32,
+    pub url: String,
+    pub content_length: String,
+    pub last_modified: String,
 }
 
 #[derive(Deserialize)]
@@ -150,6 +163,7 @@ pub struct AudioData {
     pub mime_type: String,
 }
 
+#[derive(Serialize, Debug, Clone)]
 pub struct SubtitleEntry {
     pub timestamp: u64,
     pub duration: u32,
@@ -169,12 +183,7 @@ fn parse_auth_from_content(pattern: &str, content: &str) -> Result<String, Strin
 }
 
 fn extract_id(url: &str) -> Option<String> {
-    let re = Regex::new(r"(?:v=|\/v\/|youtu\.be\/|\/embed\/|\/shorts\/)([A-Za-z0-9_-]+)").unwrap();
-
-    if let Some(captures) = re.captures(url) {
-        return captures.get(1).map(|m| m.as_str().to_string());
-    }
-    None
+    YoutubeTarget::parse(url)?.video_id().map(String::from)
 }
 
 fn preprocess_xml(xml_content: &str) -> String {
@@ -239,10 +248,27 @@ impl YoutubeAudio {
             },
             _ => client_builder.build().unwrap(),
         };
-        Self { client }
+        Self {
+            client,
+            cache: None,
+        }
+    }
+
+    // keep the player js and auth data under `cache_dir` for `ttl`
+    pub fn with_cache(mut self, cache_dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        self.cache = Some(PlayerCache::new(cache_dir.into(), ttl));
+        self
     }
 
     async fn get_auth_info(&self, video_id: &str) -> Result<AuthData, String> {
+        if let Some(auth) = self.cache.as_ref().and_then(|cache| cache.load_auth()) {
+            return Ok(AuthData {
+                po_token: None,
+                signature_timestamp: auth.signature_timestamp,
+                visitor_data: auth.visitor_data,
+            });
+        }
+
         let url = format!("https://www.youtube.com/watch?v={video_id}");
         // parse js_url
         let response = self
@@ -259,19 +285,40 @@ impl YoutubeAudio {
         let js_partial_url = parse_auth_from_content(js_pattern, &html)?;
         let js_url = format!("https://www.youtube.com{js_partial_url}");
 
-        let response_js = self
-            .client
-            .get(&js_url)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+        let cached_js = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.load_player_js(&js_partial_url));
+        let js_html = match cached_js {
+            Some(js_html) => js_html,
+            None => {
+                let response_js = self
+                    .client
+                    .get(&js_url)
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
 
-        let js_html = response_js.text().await.map_err(|e| e.to_string())?;
+                let js_html = response_js.text().await.map_err(|e| e.to_string())?;
+                if let Some(cache) = &self.cache {
+                    if let Err(e) = cache.store_player_js(&js_partial_url, &js_html) {
+                        eprintln!("Failed to cache the player js {e}");
+                    }
+                }
+                js_html
+            }
+        };
 
         let signature_timestamp = parse_auth_from_content(signature_pattern, &js_html)?;
 
         let visitor_data = parse_auth_from_content(visitor_data_pattern, &html)?;
 
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.store_auth(&js_partial_url, &visitor_data, &signature_timestamp) {
+                eprintln!("Failed to cache the auth data {e}");
+            }
+        }
+
         // let po_token = generate_po_token(&visitor_data).await;
 
         Ok(AuthData {
@@ -281,13 +328,8 @@ impl YoutubeAudio {
         })
     }
 
-    pub async fn get_video_info(&self, url: &str) -> Option<AudioData> {
-        let video_id = match extract_id(url) {
-            Some(_id) => _id,
-            None => return None,
-        };
-
-        let auth_data = self.get_auth_info(&video_id).await.ok()?;
+    async fn fetch_player(&self, video_id: &str) -> Option<ResponseBody> {
+        let auth_data = self.get_auth_info(video_id).await.ok()?;
 
         let mut headers = HeaderMap::new();
 
@@ -319,7 +361,7 @@ impl YoutubeAudio {
         const INITIAL_BACKOFF_MS: u64 = 1000;
 
         let mut attempt = 0;
-        let response_data: ResponseBody = loop {
+        loop {
             attempt += 1;
 
             match self
@@ -331,7 +373,7 @@ impl YoutubeAudio {
                 .await
             {
                 Ok(response) => match response.json().await {
-                    Ok(data) => break data,
+                    Ok(data) => return Some(data),
                     Err(e) => {
                         eprintln!("Faled to parse the info from response {e}");
 
@@ -351,8 +393,37 @@ impl YoutubeAudio {
             let backoff_duration =
                 Duration::from_millis(INITIAL_BACKOFF_MS * 2u64.pow(attempt - 1));
             tokio::time::sleep(backoff_duration).await;
+        }
+    }
+
+    pub async fn get_formats(&self, url: &str) -> Option<Vec<Format>> {
+        let video_id = extract_id(url)?;
+        let response_data = self.fetch_player(&video_id).await?;
+        Some(
+            response_data
+                .streaming_data
+                .adaptive_formats
+                .unwrap_or_default(),
+        )
+    }
+
+    pub async fn get_captions(&self, url: &str) -> Option<Vec<CaptionItem>> {
+        let video_id = extract_id(url)?;
+        let response_data = self.fetch_player(&video_id).await?;
+        Some(match response_data.captions {
+            Some(captions) => captions.player_captions_tracklist_renderer.caption_tracks,
+            None => Vec::new(),
+        })
+    }
+
+    pub async fn get_video_info(&self, url: &str) -> Option<AudioData> {
+        let video_id = match extract_id(url) {
+            Some(_id) => _id,
+            None => return None,
         };
 
+        let response_data = self.fetch_player(&video_id).await?;
+
         let mut all_formats = Vec::new();
 
         if let Some(adaptive_formats) = response_data.streaming_data.adaptive_formats {
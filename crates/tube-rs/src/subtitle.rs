@@ -0,0 +1,82 @@
+use crate::SubtitleEntry;
+
+// milliseconds to `hh:mm:ss{sep}mmm`
+fn format_timestamp(millis: u64, sep: char) -> String {
+    let hours = millis / 3_600_000;
+    let minutes = (millis % 3_600_000) / 60_000;
+    let seconds = (millis % 60_000) / 1000;
+    let millis = millis % 1000;
+    format!("{hours:02}:{minutes:02}:{seconds:02}{sep}{millis:03}")
+}
+
+pub fn to_srt(entries: &[SubtitleEntry]) -> String {
+    let mut content = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let end = entry.timestamp + entry.duration as u64;
+        content.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            format_timestamp(entry.timestamp, ','),
+            format_timestamp(end, ','),
+            entry.text.trim()
+        ));
+    }
+    content
+}
+
+pub fn to_vtt(entries: &[SubtitleEntry]) -> String {
+    let mut content = String::from("WEBVTT\n\n");
+    for entry in entries {
+        let end = entry.timestamp + entry.duration as u64;
+        content.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(entry.timestamp, '.'),
+            format_timestamp(end, '.'),
+            entry.text.trim()
+        ));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<SubtitleEntry> {
+        vec![
+            SubtitleEntry {
+                timestamp: 2400,
+                duration: 2490,
+                text: "Recently, my podcast team".to_string(),
+            },
+            SubtitleEntry {
+                timestamp: 3_661_005,
+                duration: 1000,
+                text: "an hour later\n".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn format_timestamp_works() {
+        assert_eq!(format_timestamp(0, ','), "00:00:00,000");
+        assert_eq!(format_timestamp(3_661_005, '.'), "01:01:01.005");
+    }
+
+    #[test]
+    fn to_srt_works() {
+        let srt = to_srt(&entries());
+        assert_eq!(
+            srt,
+            "1\n00:00:02,400 --> 00:00:04,890\nRecently, my podcast team\n\n\
+             2\n01:01:01,005 --> 01:01:02,005\nan hour later\n\n"
+        );
+    }
+
+    #[test]
+    fn to_vtt_works() {
+        let vtt = to_vtt(&entries());
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:02.400 --> 00:00:04.890\n"));
+        assert!(vtt.contains("01:01:01.005 --> 01:01:02.005\nan hour later\n"));
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use url::Url;
+
+const VIDEO_ID_LEN: usize = 11;
+
+// what a user supplied link points to, so the app can decide how to handle it
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", content = "id", rename_all = "camelCase")]
+pub enum YoutubeTarget {
+    Video(String),
+    Short(String),
+    Live(String),
+    Playlist(String),
+    // `@handle`, `UC...` channel id or legacy `/c/` and `/user/` names
+    Channel(String),
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn valid_video_id(id: &str) -> Option<String> {
+    if id.len() == VIDEO_ID_LEN && id.chars().all(is_id_char) {
+        Some(id.to_string())
+    } else {
+        None
+    }
+}
+
+fn valid_list_id(id: &str) -> Option<String> {
+    if !id.is_empty() && id.chars().all(is_id_char) {
+        Some(id.to_string())
+    } else {
+        None
+    }
+}
+
+fn query_value(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+}
+
+// strip the subdomains youtube serves the same pages from
+fn normalize_host(host: &str) -> &str {
+    let host = host.trim_start_matches("www.");
+    host.strip_prefix("m.")
+        .or_else(|| host.strip_prefix("music."))
+        .unwrap_or(host)
+}
+
+impl YoutubeTarget {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(id) = valid_video_id(input) {
+            return Some(Self::Video(id));
+        }
+
+        let url = if input.contains("://") {
+            Url::parse(input).ok()?
+        } else {
+            Url::parse(&format!("https://{input}")).ok()?
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        let host = url.host_str()?.to_ascii_lowercase();
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+            .unwrap_or_default();
+
+        match normalize_host(&host) {
+            "youtu.be" => valid_video_id(segments.first()?).map(Self::Video),
+            "youtube.com" | "youtube-nocookie.com" => Self::parse_path(&url, &segments),
+            _ => None,
+        }
+    }
+
+    fn parse_path(url: &Url, segments: &[&str]) -> Option<Self> {
+        match segments {
+            ["watch"] => match query_value(url, "v") {
+                Some(id) => valid_video_id(&id).map(Self::Video),
+                None => valid_list_id(&query_value(url, "list")?).map(Self::Playlist),
+            },
+            ["playlist"] | ["embed", "videoseries"] => {
+                valid_list_id(&query_value(url, "list")?).map(Self::Playlist)
+            }
+            ["shorts", id] => valid_video_id(id).map(Self::Short),
+            ["live", id] => valid_video_id(id).map(Self::Live),
+            ["embed", id] | ["v", id] | ["e", id] => valid_video_id(id).map(Self::Video),
+            ["channel", id, ..] => valid_list_id(id).map(Self::Channel),
+            ["c", name, ..] | ["user", name, ..] => Some(Self::Channel(name.to_string())),
+            [handle, ..] if handle.starts_with('@') && handle.len() > 1 => {
+                Some(Self::Channel(handle.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    // the id of a single video, none for playlists and channels
+    pub fn video_id(&self) -> Option<&str> {
+        match self {
+            Self::Video(id) | Self::Short(id) | Self::Live(id) => Some(id),
+            Self::Playlist(_) | Self::Channel(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_video_hosts_works() {
+        let test_cases = vec![
+            "FdeioVndUhs",
+            "youtube.com/watch?v=FdeioVndUhs",
+            "https://m.youtube.com/watch?v=FdeioVndUhs",
+            "https://music.youtube.com/watch?v=FdeioVndUhs&feature=share",
+            "https://www.youtube-nocookie.com/embed/FdeioVndUhs?start=10",
+            "http://youtu.be/FdeioVndUhs?si=abc",
+            "https://www.youtube.com/e/FdeioVndUhs",
+        ];
+        for input in test_cases {
+            assert_eq!(
+                YoutubeTarget::parse(input),
+                Some(YoutubeTarget::Video("FdeioVndUhs".to_string())),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_kinds_works() {
+        let test_cases = vec![
+            (
+                "https://www.youtube.com/shorts/FdeioVndUhs",
+                YoutubeTarget::Short("FdeioVndUhs".to_string()),
+            ),
+            (
+                "https://www.youtube.com/live/FdeioVndUhs?si=x",
+                YoutubeTarget::Live("FdeioVndUhs".to_string()),
+            ),
+            (
+                "https://www.youtube.com/playlist?list=PLrAXtmErZgOeiKm4sgNOknGvNjby9efdf",
+                YoutubeTarget::Playlist("PLrAXtmErZgOeiKm4sgNOknGvNjby9efdf".to_string()),
+            ),
+            (
+                "https://www.youtube.com/watch?list=PL123",
+                YoutubeTarget::Playlist("PL123".to_string()),
+            ),
+            (
+                "https://www.youtube.com/channel/UCSHZKyawb77ixDdsGog4iWA",
+                YoutubeTarget::Channel("UCSHZKyawb77ixDdsGog4iWA".to_string()),
+            ),
+            (
+                "https://www.youtube.com/@lexfridman/videos",
+                YoutubeTarget::Channel("@lexfridman".to_string()),
+            ),
+            (
+                "https://www.youtube.com/c/LexFridman",
+                YoutubeTarget::Channel("LexFridman".to_string()),
+            ),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(YoutubeTarget::parse(input), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_input() {
+        let test_cases = vec![
+            "https://example.com/watch?v=FdeioVndUhs",
+            "https://youtube.com.evil.com/watch?v=FdeioVndUhs",
+            "ftp://youtube.com/watch?v=FdeioVndUhs",
+            "https://www.youtube.com/watch?v=short",
+            "https://www.youtube.com/watch?v=FdeioVndUhs1",
+            "https://www.youtube.com/shorts/",
+            "https://www.youtube.com/@",
+            "FdeioVndUh",
+            "",
+        ];
+        for input in test_cases {
+            assert_eq!(YoutubeTarget::parse(input), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn video_id_works() {
+        assert_eq!(
+            YoutubeTarget::Live("FdeioVndUhs".to_string()).video_id(),
+            Some("FdeioVndUhs")
+        );
+        assert_eq!(
+            YoutubeTarget::Playlist("PL123".to_string()).video_id(),
+            None
+        );
+    }
+}
@@ -34,6 +34,8 @@ pub struct Audio {
     keywords: String,
     timestamp: i64,
     thumbnail_url: String,
+    // spoken language picked for transcription, none or empty to detect it
+    language: Option<String>,
 }
 
 pub fn init_db(app_handle: &AppHandle) -> Result<DataBase, DataBaseError> {
@@ -60,10 +62,13 @@ pub fn init_db(app_handle: &AppHandle) -> Result<DataBase, DataBaseError> {
             thumbnail_url TEXT NOT NULL,
             transcripts TEXT,
             summary TEXT,
+            language TEXT,
             timestamp INTEGER DEFAULT (strftime('%s', 'now'))
         )",
         [],
     )?;
+    // databases from before `language`, fails once the column is there
+    let _ = connection.execute("ALTER TABLE audio ADD COLUMN language TEXT", []);
 
     Ok(DataBase(Mutex::new(connection)))
 }
@@ -119,7 +124,7 @@ pub fn get_videos(db: State<DataBase>) -> Result<Vec<Audio>, String> {
     let db = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = db
-        .prepare("SELECT id, video_id, title, duration, upload_date, transcripts, summary, keywords, timestamp, thumbnail_url, description from audio ORDER BY id DESC")
+        .prepare("SELECT id, video_id, title, duration, upload_date, transcripts, summary, keywords, timestamp, thumbnail_url, description, language from audio ORDER BY id DESC")
         .map_err(|e| e.to_string())?;
 
     let video_iter = stmt
@@ -135,6 +140,7 @@ pub fn get_videos(db: State<DataBase>) -> Result<Vec<Audio>, String> {
                 keywords: row.get(7)?,
                 timestamp: row.get(8)?,
                 thumbnail_url: row.get(9)?,
+                language: row.get(11).ok(),
             })
         })
         .map_err(|e| e.to_string())?;
@@ -172,6 +178,32 @@ pub fn get_audio_url_with_id(
     .map_err(|e| e.to_string())
 }
 
+pub fn get_source_with_id(
+    db: State<DataBase>,
+    id: i64,
+) -> Result<(String, Option<String>), String> {
+    let db = db.0.lock().map_err(|e| e.to_string())?;
+    db.query_row(
+        "Select video_id, description from audio Where id=?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn get_context_with_id(
+    db: State<DataBase>,
+    id: i64,
+) -> Result<(String, Option<String>, Option<String>, Option<String>), String> {
+    let db = db.0.lock().map_err(|e| e.to_string())?;
+    db.query_row(
+        "Select title, keywords, description, language from audio Where id=?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )
+    .map_err(|e| e.to_string())
+}
+
 pub fn get_subtitle_with_id(db: State<DataBase>, id: i64) -> Result<(String, String), String> {
     let db = db.0.lock().map_err(|e| e.to_string())?;
     db.query_row(
@@ -182,6 +214,42 @@ pub fn get_subtitle_with_id(db: State<DataBase>, id: i64) -> Result<(String, Str
     .map_err(|e| e.to_string())
 }
 
+pub fn get_summary_with_id(db: State<DataBase>, id: i64) -> Result<Option<String>, String> {
+    let db = db.0.lock().map_err(|e| e.to_string())?;
+    db.query_row(
+        "Select summary from audio Where id=?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn get_transcripts_with_id(db: State<DataBase>, id: i64) -> Result<Option<String>, String> {
+    let db = db.0.lock().map_err(|e| e.to_string())?;
+    db.query_row(
+        "Select transcripts from audio Where id=?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+// a refreshed stream url, the stored one expires after a few hours
+pub fn update_audio_url(
+    db: State<DataBase>,
+    id: i64,
+    audio_url: &str,
+    audio_filesize: u64,
+) -> Result<(), String> {
+    let db = db.0.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE audio SET audio_url = ?1, audio_filesize = ?2 Where id=?3",
+        params![audio_url, audio_filesize, id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 pub fn update_video(
     db: State<DataBase>,
     id: i64,
@@ -198,19 +266,69 @@ pub fn update_video(
     Ok(())
 }
 
+// folders under the cache dir with files kept per video: downloaded audio,
+// waveforms, chapter stills and the audio of chunks that failed to transcribe
+const CACHED_DIRS: [&str; 4] = ["audio", "waveform", "thumbnails", "missing"];
+
+// drop what the cache keeps for `video_id`, or for every video when none; the
+// rows are already gone so files that cannot be removed are left behind
+fn remove_cached_files(app: &AppHandle, video_id: Option<&str>) {
+    let Ok(cache_dir) = app.path().cache_dir() else {
+        return;
+    };
+    for name in CACHED_DIRS {
+        let dir = cache_dir.join("newscenter").join(name);
+        let Some(video_id) = video_id else {
+            let _ = std::fs::remove_dir_all(&dir);
+            continue;
+        };
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            // e.g. audio/<id>.m4a, waveform/<id>-<config>.json or thumbnails/<id>/
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let owned = match file_name.strip_prefix(video_id) {
+                Some(rest) => rest.is_empty() || rest.starts_with(['.', '-']),
+                None => false,
+            };
+            if !owned {
+                continue;
+            }
+            let path = entry.path();
+            let _ = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+        }
+    }
+}
+
 #[tauri::command]
-pub fn delete_video(db: State<DataBase>, id: i64) -> Result<(), String> {
+pub fn delete_video(app: AppHandle, db: State<DataBase>, id: i64) -> Result<(), String> {
     let db = db.0.lock().map_err(|e| e.to_string())?;
+    let video_id: Option<String> = db
+        .query_row(
+            "Select video_id from audio Where id=?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .ok();
     db.execute("DELETE From audio WHERE id =?1", params![id])
         .map_err(|e| e.to_string())?;
+    if let Some(video_id) = video_id {
+        remove_cached_files(&app, Some(&video_id));
+    }
     Ok(())
 }
 
 #[tauri::command]
-pub fn clear_all(db: State<DataBase>) -> Result<(), String> {
+pub fn clear_all(app: AppHandle, db: State<DataBase>) -> Result<(), String> {
     let db = db.0.lock().map_err(|e| e.to_string())?;
     db.execute("DELETE FROM audio", [])
         .map_err(|e| e.to_string())?;
+    remove_cached_files(&app, None);
     Ok(())
     // let mut db = db.0.lock().map_err(|e| e.to_string())?;
     // let tx = db.transaction().map_err(|e| e.to_string())?;
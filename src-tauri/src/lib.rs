@@ -4,15 +4,329 @@ use tauri::{Emitter, Manager};
 mod db;
 mod gemini;
 mod setting;
+mod transcriber;
 mod utils;
 mod whisper;
-use tube_rs::YoutubeAudio;
+mod whisper_cpp;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tube_rs::{AudioData, YoutubeAudio, YoutubeTarget};
+
+const PLAYER_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+// seconds of audio shared by neighbouring chunks, stitched back in whisper::trancript
+const CHUNK_OVERLAP: f64 = 3.0;
+// audio past this share of the transcriber's upload limit is split, into chunks
+// aimed at the smaller share so bitrate swings stay under the limit
+const SPLIT_THRESHOLD: f64 = 0.9;
+const CHUNK_TARGET: f64 = 0.8;
+// rows imported from a media file on disk keep its path behind this in `audio_url`
+const LOCAL_FILE_PREFIX: &str = "file://";
+
+// the tokens of the splits in progress by input id, for `cancel_split`
+#[derive(Default)]
+struct SplitState(Mutex<HashMap<i64, ffmpeg_audio::CancelToken>>);
+
+// payload of `split_progress` events
+#[derive(Clone, Serialize)]
+struct SplitProgress {
+    input_id: i64,
+    percent: f64,
+}
+
+fn build_youtube_audio(app: &tauri::AppHandle) -> YoutubeAudio {
+    let cache_dir = app.path().cache_dir().unwrap();
+    YoutubeAudio::new(setting::get_proxy(app).as_deref()).with_cache(
+        cache_dir.join("newscenter").join("player"),
+        PLAYER_CACHE_TTL,
+    )
+}
+
+// the audio of a video, kept in the cache so clips can be cut from it later
+async fn download_video_audio(
+    app: &tauri::AppHandle,
+    youtube_audio: &YoutubeAudio,
+    id: i64,
+) -> Result<(PathBuf, ffmpeg_audio::MediaInfo), String> {
+    let (audio_url, audio_filesize, mime_type, _) = db::get_audio_url_with_id(app.state(), id)?;
+    let (video_id, _) = db::get_source_with_id(app.state(), id)?;
+    if let Some(path) = local_file(&audio_url) {
+        let info = ffmpeg_audio::probe(&path)
+            .ok()
+            .filter(|info| info.audio().is_some())
+            .ok_or("the imported file is gone or has no audio stream")?;
+        return Ok((path, info));
+    }
+    let suffix = if mime_type.contains("webm") {
+        "webm"
+    } else {
+        "m4a"
+    };
+    let audio_dir = app
+        .path()
+        .cache_dir()
+        .unwrap()
+        .join("newscenter")
+        .join("audio");
+    std::fs::create_dir_all(&audio_dir).map_err(|e| e.to_string())?;
+    let audio_path = audio_dir.join(format!("{}.{}", video_id, suffix));
+    // youtube's reported size and duration can be off, measure the download itself
+    let probe_audio = |path: &Path| {
+        ffmpeg_audio::probe(path)
+            .ok()
+            .filter(|info| info.audio().is_some())
+    };
+    if let Some(info) = probe_audio(&audio_path) {
+        return Ok((audio_path, info));
+    }
+
+    youtube_audio
+        .download_audio(&audio_url, audio_filesize, &audio_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(info) = probe_audio(&audio_path) {
+        return Ok((audio_path, info));
+    }
+    // the stored stream url expires after a few hours, ask for a fresh one
+    let audio_data = youtube_audio
+        .get_video_info(&video_id)
+        .await
+        .ok_or("failed to parse audio info")?;
+    db::update_audio_url(
+        app.state(),
+        id,
+        &audio_data.audio_url,
+        audio_data.audio_filesize,
+    )?;
+    youtube_audio
+        .download_audio(
+            &audio_data.audio_url,
+            audio_data.audio_filesize,
+            &audio_path,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    match probe_audio(&audio_path) {
+        Some(info) => Ok((audio_path, info)),
+        None => Err("downloaded file has no audio stream".to_string()),
+    }
+}
+
+// the path of a media file imported from disk, none for youtube videos
+fn local_file(audio_url: &str) -> Option<PathBuf> {
+    audio_url.strip_prefix(LOCAL_FILE_PREFIX).map(PathBuf::from)
+}
+
+// a row for a media file on disk, transcribed like a video but read in place
+fn import_local_file(app: &tauri::AppHandle, path: &Path) -> Result<i64, String> {
+    let path = path.canonicalize().map_err(|e| e.to_string())?;
+    let info = ffmpeg_audio::probe(&path).map_err(|e| e.to_string())?;
+    if info.audio().is_none() {
+        return Err("the file has no audio stream".to_string());
+    }
+    // the same file always maps to the same cache entries
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    // in microseconds like youtube's last modified time
+    let modified = std::fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_micros() as u64);
+    let audio_data = AudioData {
+        video_id: format!("local-{:016x}", hasher.finish()),
+        title: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        duration: info.duration as u64,
+        timestamp: modified,
+        keywords: None,
+        description: None,
+        caption_lang: None,
+        caption_url: None,
+        audio_url: format!("{}{}", LOCAL_FILE_PREFIX, path.display()),
+        audio_filesize: info.size,
+        thumbnail_url: String::new(),
+        mime_type: ffmpeg_audio::mime_type(&path).to_string(),
+    };
+    db::create_video(app.state(), audio_data)
+}
+
+// transcript from a text subtitle stream of a local media file, none when it has no
+// usable subtitles and needs to be transcribed
+async fn read_subtitles(path: PathBuf) -> Result<Option<Vec<whisper::Segment>>, String> {
+    tokio::task::spawn_blocking(move || {
+        let tracks = ffmpeg_audio::list_subtitle_tracks(&path).map_err(|e| e.to_string())?;
+        let track = tracks
+            .iter()
+            .find(|track| matches!(track.language.as_deref(), Some("eng" | "en")))
+            .or(tracks.first());
+        let Some(track) = track else {
+            return Ok(None);
+        };
+        let cues =
+            ffmpeg_audio::extract_subtitles(&path, track.index).map_err(|e| e.to_string())?;
+        if cues.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(utils::transform_cues_to_segments(cues)))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// stream a finished transcript to the window and store it
+fn publish_transcript(
+    app: &tauri::AppHandle,
+    id: i64,
+    segments: &[whisper::Segment],
+) -> Result<(), String> {
+    app.emit("stream", "[start]".to_string())
+        .map_err(|e| e.to_string())?;
+    for segment in segments {
+        app.emit("stream", segment.text.clone())
+            .map_err(|e| e.to_string())?
+    }
+    app.emit("stream", "[end]".to_string())
+        .map_err(|e| e.to_string())?;
+
+    let transcripts = serde_json::to_string(segments).unwrap();
+    db::update_video(app.state(), id, "transcripts".to_string(), transcripts)
+}
+
+// the file sent to the transcriber, its media info and the map from its timeline
+// back to the video's when it was cut or sped up; named after `input_id` so
+// concurrent runs keep their own
+#[cfg(feature = "ffmpeg")]
+fn prepare_upload(
+    app: &tauri::AppHandle,
+    input_id: i64,
+    required_codec: Option<ffmpeg_audio::SpeechCodec>,
+    source_path: PathBuf,
+    downloaded: ffmpeg_audio::MediaInfo,
+) -> Result<
+    (
+        PathBuf,
+        ffmpeg_audio::MediaInfo,
+        Option<ffmpeg_audio::TimeMap>,
+    ),
+    String,
+> {
+    // 16 kHz mono at a speech bitrate keeps most videos within a single request
+    let (codec, bitrate) = setting::get_upload_format(app);
+    let codec = required_codec.unwrap_or(codec);
+    let upload_path = app
+        .path()
+        .cache_dir()
+        .unwrap()
+        .join("newscenter")
+        .join(format!("upload_{}.{}", input_id, codec.extension()));
+    let mut transcoder = ffmpeg_audio::Transcoder::new(codec).with_bitrate(bitrate);
+    let mut time_map = None;
+    let speech_only = setting::get_settings(app)
+        .and_then(|settings| settings.speech_only)
+        .unwrap_or(false);
+    if speech_only {
+        let regions =
+            ffmpeg_audio::detect_speech(&source_path, &ffmpeg_audio::VadConfig::default())
+                .map_err(|e| e.to_string())?;
+        if !regions.is_empty() {
+            transcoder = transcoder.with_filter(ffmpeg_audio::speech_filter(&regions));
+            time_map = Some(ffmpeg_audio::TimeMap::new(&regions));
+        }
+    }
+    if let Some(spec) = setting::get_preprocess(app).filter_spec() {
+        transcoder = transcoder.with_filter(spec);
+    }
+    let speed_factor = setting::get_speed_factor(app);
+    if speed_factor > 1.0 {
+        transcoder = transcoder.with_tempo(speed_factor);
+        time_map = Some(time_map.unwrap_or_default().with_tempo(speed_factor));
+    }
+    match transcoder.transcode(&source_path, &upload_path) {
+        Ok(()) => {
+            let info = ffmpeg_audio::probe(&upload_path).map_err(|e| e.to_string())?;
+            Ok((upload_path, info, time_map))
+        }
+        // the encoder may be missing from the local ffmpeg build, upload the original
+        // at its own speed unless the transcriber cannot read anything else
+        Err(e) if required_codec.is_none() && ffmpeg_audio::is_missing_encoder(&e) => {
+            Ok((source_path, downloaded, None))
+        }
+        Err(e) if required_codec.is_some() => Err(format!(
+            "the transcriber needs {} audio and the conversion failed: {}",
+            codec.extension(),
+            e
+        )),
+        Err(e) => Err(format!("failed to prepare the upload: {}", e)),
+    }
+}
+
+// without libav nothing can be encoded, the download is decoded to 16 kHz wav
+// instead and the filters behind speech only and the speed factor are skipped
+#[cfg(not(feature = "ffmpeg"))]
+fn prepare_upload(
+    app: &tauri::AppHandle,
+    input_id: i64,
+    _required_codec: Option<ffmpeg_audio::SpeechCodec>,
+    source_path: PathBuf,
+    _downloaded: ffmpeg_audio::MediaInfo,
+) -> Result<
+    (
+        PathBuf,
+        ffmpeg_audio::MediaInfo,
+        Option<ffmpeg_audio::TimeMap>,
+    ),
+    String,
+> {
+    let upload_path = app
+        .path()
+        .cache_dir()
+        .unwrap()
+        .join("newscenter")
+        .join(format!("upload_{}.wav", input_id));
+    ffmpeg_audio::native::resample_to_wav(
+        &source_path,
+        &upload_path,
+        ffmpeg_audio::SPEECH_SAMPLE_RATE,
+    )
+    .map_err(|e| format!("failed to prepare the upload: {}", e))?;
+    let info = ffmpeg_audio::probe(&upload_path).map_err(|e| e.to_string())?;
+    Ok((upload_path, info, None))
+}
 
 #[tauri::command(rename_all = "snake_case")]
-async fn run_yt(app: tauri::AppHandle, url: &str, input_id: i64) -> Result<(), String> {
+async fn run_yt(
+    app: tauri::AppHandle,
+    url: &str,
+    input_id: i64,
+    // spoken language, `auto` or none to let the transcriber detect it
+    language: Option<String>,
+) -> Result<(), String> {
     let mut _id = input_id;
-    let youtube_audio = YoutubeAudio::new(setting::get_proxy(&app).as_deref());
+    let cache_dir = app.path().cache_dir().unwrap();
+    let youtube_audio = build_youtube_audio(&app);
+    if _id == -1 && Path::new(url).is_file() {
+        _id = import_local_file(&app, Path::new(url))?;
+        app.emit("state", "update video")
+            .map_err(|e| e.to_string())?;
+    }
     if _id == -1 {
+        match YoutubeTarget::parse(url) {
+            Some(target) if target.video_id().is_some() => {}
+            Some(YoutubeTarget::Playlist(_)) => {
+                return Err("playlists are not supported yet".to_string())
+            }
+            Some(YoutubeTarget::Channel(_)) => {
+                return Err("channels are not supported yet".to_string())
+            }
+            _ => return Err("not a valid youtube url".to_string()),
+        };
         let audio_data = match youtube_audio.get_video_info(url).await {
             Some(data) => data,
             None => return Err("failed to parse audio info".to_string()),
@@ -21,64 +335,408 @@ async fn run_yt(app: tauri::AppHandle, url: &str, input_id: i64) -> Result<(), S
         app.emit("state", "update video")
             .map_err(|e| e.to_string())?;
     };
+    if let Some(language) = language {
+        let language = transcriber::normalize_language(&language).unwrap_or_default();
+        db::update_video(app.state(), _id, "language".to_string(), language)?;
+    }
     if let Ok((Some(lang), Some(url))) = db::get_caption_with_id(app.state(), _id) {
         let subtitles = youtube_audio
             .download_caption(&url, &lang)
             .await
             .map_err(|e| e.to_string())?;
-        app.emit("stream", "[start]".to_string())
-            .map_err(|e| e.to_string())?;
-        for subtitle in &subtitles {
-            app.emit("stream", subtitle.text.clone())
-                .map_err(|e| e.to_string())?
-        }
-        app.emit("stream", "[end]".to_string())
-            .map_err(|e| e.to_string())?;
-
         let segments = utils::transform_subtitles_to_segments(subtitles);
-        let transcripts = serde_json::to_string(&segments).unwrap();
-        db::update_video(app.state(), _id, "transcripts".to_string(), transcripts)?;
-        return Ok(());
+        return publish_transcript(&app, _id, &segments);
     };
+    // imported files that carry subtitles skip transcription like captioned videos
+    let (audio_url, _, _, _) = db::get_audio_url_with_id(app.state(), _id)?;
+    if let Some(path) = local_file(&audio_url) {
+        if let Some(segments) = read_subtitles(path).await? {
+            return publish_transcript(&app, _id, &segments);
+        }
+    }
 
-    let (audio_url, audio_filesize, mime_type, duration) =
-        db::get_audio_url_with_id(app.state(), _id)?;
-    let cache_dir = app.path().cache_dir().unwrap();
-    let file_path = if mime_type.contains("webm") {
-        "temp.webm"
-    } else {
-        "temp.m4a"
-    };
-    let mut temp_path = cache_dir.join("newscenter").join(file_path);
-    youtube_audio
-        .download_audio(&audio_url, audio_filesize, &temp_path)
-        .await
-        .map_err(|e| e.to_string())?;
+    let transcriber = transcriber::get_transcriber(&app)?;
+    let context = transcriber::get_context(&app, _id)?;
+    let (source_path, downloaded) = download_video_audio(&app, &youtube_audio, _id).await?;
 
-    if audio_filesize > 22 * 1024 * 1024 {
-        let output_dir = cache_dir.join("chunk");
-        let bytes_per_second = audio_filesize as f64 / duration as f64;
-        let chunk_duration = ((20 * 1024 * 1024) as f64 / bytes_per_second) as i64;
+    let required_codec = transcriber.required_codec();
+    // decoding for speech detection and the transcode take a while, keep them off
+    // the async runtime
+    let handle = app.clone();
+    let source = source_path.clone();
+    let (audio_path, audio_info, time_map) = tokio::task::spawn_blocking(move || {
+        prepare_upload(&handle, _id, required_codec, source_path, downloaded)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let (audio_size, audio_duration) = (audio_info.size, audio_info.duration);
+    // the upload is ours to remove, unless it fell back to the download itself
+    let upload_path = (audio_path != source).then(|| audio_path.clone());
 
-        let auido_splitter = ffmpeg_audio::AudioSplitter::new(chunk_duration);
-        auido_splitter
-            .split(&temp_path, &output_dir)
-            .map_err(|e| e.to_string())?;
+    let output_dir = cache_dir.join("chunk").join(_id.to_string());
+    let chunks = match transcriber.max_upload_bytes() {
+        Some(max_bytes) if audio_size as f64 > max_bytes as f64 * SPLIT_THRESHOLD => {
+            let bytes_per_second = audio_size as f64 / audio_duration;
+            let chunk_duration = (max_bytes as f64 * CHUNK_TARGET / bytes_per_second) as i64;
 
-        temp_path = output_dir;
+            let auido_splitter = ffmpeg_audio::AudioSplitter::new(chunk_duration)
+                .with_silence_search(ffmpeg_audio::SilenceConfig::default())
+                .with_overlap(CHUNK_OVERLAP)
+                .with_max_bytes(max_bytes);
+            split_audio(&app, _id, auido_splitter, audio_path, output_dir.clone()).await?
+        }
+        // small enough, or a transcriber without an upload limit
+        _ => vec![ffmpeg_audio::ChunkInfo::whole(audio_path, audio_duration)],
     };
 
     app.emit("stream", "[start]".to_string())
         .map_err(|e| e.to_string())?;
-    let segments = whisper::trancript(&app, &temp_path).await?;
+    let (segments, mut failed) = whisper::trancript(
+        &app,
+        transcriber.as_ref(),
+        &chunks,
+        time_map.as_ref(),
+        &context,
+    )
+    .await?;
     app.emit("stream", "[end]".to_string())
         .map_err(|e| e.to_string())?;
+    // the gaps stay marked in the transcript until `retry_missing_chunks` fills them
+    let (video_id, _) = db::get_source_with_id(app.state(), _id)?;
+    whisper::keep_failed_chunks(&app, &video_id, &failed, time_map.as_ref())
+        .await
+        .map_err(|e| e.to_string())?;
+    // only this run's chunks, others may still be splitting next to them
+    if output_dir.is_dir() {
+        whisper::remove_files_from_directory(&output_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::remove_dir(&output_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(upload_path) = upload_path {
+        tokio::fs::remove_file(&upload_path)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    // nothing came back, the audio is kept but there is no transcript to store
+    if failed.len() == chunks.len() {
+        let error = failed.pop().map(|chunk| chunk.error);
+        return Err(error.unwrap_or_else(|| "nothing to transcribe".to_string()));
+    }
     let transcripts = serde_json::to_string(&segments).unwrap();
     db::update_video(app.state(), _id, "transcripts".to_string(), transcripts)?;
 
     Ok(())
 }
 
+// split on a blocking thread so the runtime keeps serving other commands, the
+// percent done goes out as `split_progress` events tagged with `input_id`
+async fn split_audio(
+    app: &tauri::AppHandle,
+    input_id: i64,
+    splitter: ffmpeg_audio::AudioSplitter,
+    audio_path: PathBuf,
+    output_dir: PathBuf,
+) -> Result<Vec<ffmpeg_audio::ChunkInfo>, String> {
+    let cancel = ffmpeg_audio::CancelToken::new();
+    app.state::<SplitState>()
+        .0
+        .lock()
+        .unwrap()
+        .insert(input_id, cancel.clone());
+
+    let progress_app = app.clone();
+    let split_cancel = cancel.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        splitter
+            .split_with_progress(&audio_path, &output_dir, &split_cancel, |percent| {
+                let _ = progress_app.emit("split_progress", SplitProgress { input_id, percent });
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    app.state::<SplitState>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(&input_id);
+    if cancel.is_cancelled() {
+        return Err("split cancelled".to_string());
+    }
+    result
+}
+
+#[tauri::command(rename_all = "snake_case")]
+fn cancel_split(state: tauri::State<SplitState>, input_id: i64) {
+    if let Some(cancel) = state.0.lock().unwrap().get(&input_id) {
+        cancel.cancel();
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum ClipRange {
+    // index into the chapters of `video_chapters`, as the stills and reports use
+    Chapter { index: usize },
+    Range { start: f64, end: f64 },
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn export_clip(
+    app: tauri::AppHandle,
+    input_id: i64,
+    range: ClipRange,
+    output_path: String,
+) -> Result<String, String> {
+    let youtube_audio = build_youtube_audio(&app);
+    let (audio_path, audio_info) = download_video_audio(&app, &youtube_audio, input_id).await?;
+    let (start, end) = match range {
+        ClipRange::Chapter { index } => *video_chapters(&app, input_id, audio_info.duration)?
+            .get(index)
+            .ok_or("chapter not found")?,
+        ClipRange::Range { start, end } => (start, end.min(audio_info.duration)),
+    };
+    if end <= start {
+        return Err("empty clip range".to_string());
+    }
+    let clip = tokio::task::spawn_blocking(move || {
+        ffmpeg_audio::extract_clip(&audio_path, Path::new(&output_path), start, end)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(clip.path.to_string_lossy().to_string())
+}
+
+fn stored_segments(app: &tauri::AppHandle, input_id: i64) -> Result<Vec<whisper::Segment>, String> {
+    let transcripts =
+        db::get_transcripts_with_id(app.state(), input_id)?.ok_or("video has no transcript")?;
+    serde_json::from_str(&transcripts).map_err(|e| e.to_string())
+}
+
+// the stored transcript with its word timings, for the player to seek and
+// highlight along
+#[tauri::command(rename_all = "snake_case")]
+fn get_segments(app: tauri::AppHandle, input_id: i64) -> Result<Vec<whisper::Segment>, String> {
+    stored_segments(&app, input_id)
+}
+
+// the video's audio for the player as raw bytes, downloaded first when it is not
+// cached yet
+#[tauri::command(rename_all = "snake_case")]
+async fn load_audio(app: tauri::AppHandle, input_id: i64) -> Result<tauri::ipc::Response, String> {
+    let youtube_audio = build_youtube_audio(&app);
+    let (audio_path, _) = download_video_audio(&app, &youtube_audio, input_id).await?;
+    let bytes = tokio::fs::read(&audio_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
+// the stored transcript as an .srt or .vtt file, picked by the extension of
+// `output_path`
+#[tauri::command(rename_all = "snake_case")]
+fn export_subtitles(
+    app: tauri::AppHandle,
+    input_id: i64,
+    output_path: String,
+) -> Result<String, String> {
+    let segments = stored_segments(&app, input_id)?;
+    let output_path = Path::new(&output_path);
+    let content = match output_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("srt") => utils::segments_to_srt(&segments),
+        Some("vtt") => utils::segments_to_vtt(&segments),
+        _ => return Err("subtitles have to be .srt or .vtt".to_string()),
+    };
+    std::fs::write(output_path, content).map_err(|e| e.to_string())?;
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+// audiowaveform style peaks for the video's audio, generated once and cached
+#[tauri::command(rename_all = "snake_case")]
+async fn get_waveform(app: tauri::AppHandle, input_id: i64) -> Result<serde_json::Value, String> {
+    let (video_id, _) = db::get_source_with_id(app.state(), input_id)?;
+    let waveform_dir = app
+        .path()
+        .cache_dir()
+        .unwrap()
+        .join("newscenter")
+        .join("waveform");
+    let config = ffmpeg_audio::PeaksConfig::default();
+    let waveform_path = waveform_dir.join(format!("{}-{}.json", video_id, config.key()));
+
+    let json = match std::fs::read_to_string(&waveform_path) {
+        Ok(json) => json,
+        Err(_) => {
+            let youtube_audio = build_youtube_audio(&app);
+            let (audio_path, _) = download_video_audio(&app, &youtube_audio, input_id).await?;
+            let waveform = tokio::task::spawn_blocking(move || {
+                ffmpeg_audio::generate_peaks(&audio_path, &config).map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())??;
+            let json = waveform.to_json();
+            std::fs::create_dir_all(&waveform_dir).map_err(|e| e.to_string())?;
+            std::fs::write(&waveform_path, &json).map_err(|e| e.to_string())?;
+            json
+        }
+    };
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+#[derive(Serialize)]
+struct ChapterThumbnail {
+    // chapter index, 0 for the whole video when neither the summary nor the
+    // description lists chapters
+    index: usize,
+    start: f64,
+    end: f64,
+    // where the keyframe actually is
+    time: f64,
+    path: String,
+}
+
+// the chapters of the stored summary, or the description's when it has none
+fn video_chapters(
+    app: &tauri::AppHandle,
+    input_id: i64,
+    duration: f64,
+) -> Result<Vec<(f64, f64)>, String> {
+    let summary = db::get_summary_with_id(app.state(), input_id)?.unwrap_or_default();
+    let mut chapters = utils::chapter_ranges(&summary, duration);
+    if chapters.is_empty() {
+        let (_, description) = db::get_source_with_id(app.state(), input_id)?;
+        chapters = utils::chapter_ranges(&description.unwrap_or_default(), duration);
+    }
+    if chapters.is_empty() {
+        chapters.push((0.0, duration));
+    }
+    Ok(chapters)
+}
+
+// a still per chapter taken from the keyframe at or before each chapter's
+// midpoint, of `video_path` or else of the imported file the row was made from
+#[tauri::command(rename_all = "snake_case")]
+async fn chapter_thumbnails(
+    app: tauri::AppHandle,
+    input_id: i64,
+    video_path: Option<String>,
+) -> Result<Vec<ChapterThumbnail>, String> {
+    let (video_id, _) = db::get_source_with_id(app.state(), input_id)?;
+    let video_path = match video_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let (audio_url, _, _, _) = db::get_audio_url_with_id(app.state(), input_id)?;
+            local_file(&audio_url).ok_or("stills need a local copy of the video")?
+        }
+    };
+    let duration = ffmpeg_audio::probe(&video_path)
+        .map_err(|e| e.to_string())?
+        .duration;
+    let chapters = video_chapters(&app, input_id, duration)?;
+    let times: Vec<f64> = chapters
+        .iter()
+        .map(|(start, end)| (start + end) / 2.0)
+        .collect();
+
+    let output_dir = app
+        .path()
+        .cache_dir()
+        .unwrap()
+        .join("newscenter")
+        .join("thumbnails")
+        .join(&video_id);
+    let thumbnails = tokio::task::spawn_blocking(move || {
+        ffmpeg_audio::extract_keyframes(
+            &video_path,
+            &times,
+            &output_dir,
+            &ffmpeg_audio::ThumbnailConfig::default(),
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    Ok(thumbnails
+        .into_iter()
+        .map(|thumbnail| {
+            let (start, end) = chapters[thumbnail.index];
+            ChapterThumbnail {
+                index: thumbnail.index,
+                start,
+                end,
+                time: thumbnail.time,
+                path: thumbnail.path.to_string_lossy().to_string(),
+            }
+        })
+        .collect())
+}
+
+// the stored summary as a markdown file, with a still under every chapter when
+// the video is on disk; the stills are copied into `<name>_files` next to it
+#[tauri::command(rename_all = "snake_case")]
+async fn export_report(
+    app: tauri::AppHandle,
+    input_id: i64,
+    output_path: String,
+    video_path: Option<String>,
+) -> Result<String, String> {
+    let summary = db::get_summary_with_id(app.state(), input_id)?.ok_or("video has no summary")?;
+    let (title, _, _, _) = db::get_context_with_id(app.state(), input_id)?;
+    let output_path = PathBuf::from(output_path);
+    // an imported audio file has nothing to take stills from
+    let has_video = match &video_path {
+        Some(_) => true,
+        None => {
+            let (audio_url, _, _, _) = db::get_audio_url_with_id(app.state(), input_id)?;
+            local_file(&audio_url)
+                .and_then(|path| ffmpeg_audio::probe(&path).ok())
+                .is_some_and(|info| {
+                    info.streams_of(ffmpeg_audio::StreamKind::Video)
+                        .next()
+                        .is_some()
+                })
+        }
+    };
+
+    let mut stills = Vec::new();
+    if has_video {
+        let stem = output_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let files_name = format!("{}_files", stem);
+        let files_dir = output_path.with_file_name(&files_name);
+        std::fs::create_dir_all(&files_dir).map_err(|e| e.to_string())?;
+        for thumbnail in chapter_thumbnails(app.clone(), input_id, video_path).await? {
+            let still = Path::new(&thumbnail.path);
+            let file_name = still.file_name().ok_or("still without a file name")?;
+            std::fs::copy(still, files_dir.join(file_name)).map_err(|e| e.to_string())?;
+            stills.push((
+                thumbnail.start,
+                format!("{}/{}", files_name, file_name.to_string_lossy()),
+            ));
+        }
+    }
+
+    let report = utils::summary_report(&title, &summary, &stills);
+    std::fs::write(&output_path, report).map_err(|e| e.to_string())?;
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn parse_url(url: &str) -> Option<YoutubeTarget> {
+    YoutubeTarget::parse(url)
+}
+
 #[tauri::command]
 async fn fetch_image(app: tauri::AppHandle, url: String) -> Result<Vec<u8>, String> {
     let client = whisper::create_client(&app)
@@ -98,13 +756,24 @@ pub fn run() {
             setting::get_config_path(app.handle());
             let database = db::init_db(app.handle())?;
             app.manage(database);
+            app.manage(SplitState::default());
             Ok(())
         })
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             run_yt,
+            cancel_split,
+            export_clip,
+            export_subtitles,
+            get_segments,
+            load_audio,
+            get_waveform,
+            chapter_thumbnails,
+            export_report,
+            parse_url,
             fetch_image,
             whisper::run_summary,
+            whisper::retry_missing_chunks,
             db::get_videos,
             db::delete_video,
             db::clear_all,
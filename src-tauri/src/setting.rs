@@ -1,3 +1,4 @@
+use ffmpeg_audio::{Preprocess, SpeechCodec};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -9,12 +10,39 @@ pub struct AppSettings {
     pub api_key: Option<String>,
     pub ai_url: Option<String>,
     pub ai_model_name: Option<String>,
+    // `openai`, `groq`, `local` or `asr`, see `transcriber::from_settings`
+    pub transcriber: Option<String>,
     pub whisper_api_key: Option<String>,
     pub whisper_url: Option<String>,
     pub whisper_model_name: Option<String>,
+    // path to a ggml model, transcribes offline with the whisper.cpp sidecar when set
+    pub whisper_local_model: Option<String>,
     pub proxy: Option<String>,
+    // `opus`, `flac` or `mp3`, audio is re-encoded to this before upload
+    pub upload_format: Option<String>,
+    // kbps
+    pub upload_bitrate: Option<String>,
+    // cut silence and music out before upload, timestamps are mapped back afterwards
+    pub speech_only: Option<bool>,
+    // cleanup before upload, see `ffmpeg_audio::Preprocess`
+    pub downmix: Option<bool>,
+    // Hz
+    pub highpass: Option<String>,
+    pub denoise: Option<bool>,
+    pub loudnorm: Option<bool>,
+    // play the upload this many times faster, whisper is billed per audio minute
+    pub speed_factor: Option<String>,
+    // chunks transcribed at the same time
+    pub transcribe_concurrency: Option<String>,
 }
 
+const DEFAULT_UPLOAD_BITRATE: usize = 32;
+// whisper drops words quickly past this
+const MAX_SPEED_FACTOR: f64 = 3.0;
+const DEFAULT_CONCURRENCY: usize = 3;
+// hosted apis rate limit well before this
+const MAX_CONCURRENCY: usize = 8;
+
 pub fn get_config_path(app: &tauri::AppHandle) -> PathBuf {
     let mut path = app
         .path()
@@ -48,6 +76,54 @@ pub fn get_settings(app: &tauri::AppHandle) -> Option<AppSettings> {
     }
 }
 
+// codec and bitrate in bits per second for the audio sent to whisper
+pub fn get_upload_format(app: &tauri::AppHandle) -> (SpeechCodec, usize) {
+    let settings = get_settings(app).unwrap_or_default();
+    let codec = settings
+        .upload_format
+        .as_deref()
+        .and_then(SpeechCodec::from_name)
+        .unwrap_or(SpeechCodec::Opus);
+    let bitrate = settings
+        .upload_bitrate
+        .as_deref()
+        .and_then(|kbps| kbps.trim().parse::<usize>().ok())
+        .filter(|&kbps| kbps > 0)
+        .unwrap_or(DEFAULT_UPLOAD_BITRATE);
+    (codec, bitrate * 1000)
+}
+
+pub fn get_preprocess(app: &tauri::AppHandle) -> Preprocess {
+    let settings = get_settings(app).unwrap_or_default();
+    Preprocess {
+        downmix: settings.downmix.unwrap_or(false),
+        highpass: settings
+            .highpass
+            .as_deref()
+            .and_then(|hz| hz.trim().parse().ok()),
+        denoise: settings.denoise.unwrap_or(false),
+        loudnorm: settings.loudnorm.unwrap_or(false),
+    }
+}
+
+// 1.0 when unset, clamped to what whisper still transcribes well
+pub fn get_speed_factor(app: &tauri::AppHandle) -> f64 {
+    get_settings(app)
+        .and_then(|settings| settings.speed_factor)
+        .and_then(|factor| factor.trim().trim_end_matches('x').parse::<f64>().ok())
+        .filter(|factor| factor.is_finite())
+        .map(|factor| factor.clamp(1.0, MAX_SPEED_FACTOR))
+        .unwrap_or(1.0)
+}
+
+pub fn get_concurrency(app: &tauri::AppHandle) -> usize {
+    get_settings(app)
+        .and_then(|settings| settings.transcribe_concurrency)
+        .and_then(|limit| limit.trim().parse::<usize>().ok())
+        .map(|limit| limit.clamp(1, MAX_CONCURRENCY))
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
 #[tauri::command]
 pub fn load_settings(app: tauri::AppHandle) -> Result<AppSettings, String> {
     let path = get_config_path(&app);
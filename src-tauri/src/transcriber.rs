@@ -0,0 +1,609 @@
+// the speech to text services a run can use, picked from `AppSettings::transcriber`
+use ffmpeg_audio::SpeechCodec;
+use futures_util::future::BoxFuture;
+use reqwest::multipart::{Form, Part};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+use tokio::time::Instant;
+
+use super::db;
+use super::setting::{self, AppSettings};
+use super::whisper::{attach_words, create_client, Segment, Word};
+use super::whisper_cpp;
+
+// the hosted apis reject files over 25 MB, keep some headroom
+const OPENAI_MAX_UPLOAD_BYTES: u64 = 24_000_000;
+const GROQ_URL: &str = "https://api.groq.com/openai/v1/audio/transcriptions";
+const GROQ_MODEL: &str = "whisper-large-v3";
+const MAX_ATTEMPTS: u32 = 4;
+// doubled after every failed attempt
+const BASE_DELAY: Duration = Duration::from_secs(2);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+// a `Retry-After` longer than this is treated as a rejection for today
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+// whisper only reads the last 224 tokens of a prompt, kept under with room for
+// the estimate being off
+const MAX_PROMPT_TOKENS: usize = 200;
+// the ISO-639-1 codes whisper was trained on
+const WHISPER_LANGUAGES: [&str; 97] = [
+    "af", "am", "ar", "as", "az", "ba", "be", "bg", "bn", "bo", "br", "bs", "ca", "cs", "cy", "da",
+    "de", "el", "en", "es", "et", "eu", "fa", "fi", "fo", "fr", "gl", "gu", "ha", "he", "hi", "hr",
+    "ht", "hu", "hy", "id", "is", "it", "ja", "ka", "kk", "km", "kn", "ko", "la", "lb", "ln", "lo",
+    "lt", "lv", "mg", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "ne", "nl", "nn", "no", "oc",
+    "pa", "pl", "ps", "pt", "ro", "ru", "sa", "sd", "si", "sk", "sl", "sn", "so", "sq", "sr", "su",
+    "sv", "sw", "ta", "te", "tg", "th", "tk", "tl", "tr", "tt", "uk", "ur", "uz", "vi", "yi", "yo",
+    "zh",
+];
+
+// what is known about a video before hearing it, sent along with every chunk
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeContext {
+    // ISO-639-1, none lets the model detect it
+    pub language: Option<String>,
+    // names and jargon from the video's page, for the model to spell them the same way
+    pub prompt: Option<String>,
+}
+
+// `en`, `EN` or `en-US` to `en`, none for `auto` and anything whisper does not know
+pub fn normalize_language(language: &str) -> Option<String> {
+    let language = language.trim().to_ascii_lowercase();
+    let code = language.split(['-', '_']).next().unwrap_or_default();
+    WHISPER_LANGUAGES.contains(&code).then(|| code.to_string())
+}
+
+// whisper's tokenizer takes about four latin letters per token but a token or
+// more for every other character, so those count as one each
+fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(char::is_ascii).count();
+    ascii.div_ceil(4) + text.chars().count() - ascii
+}
+
+// the longest start of `text` within `budget` estimated tokens, cut at a word
+fn fit_tokens(text: &str, budget: usize) -> &str {
+    // in quarter tokens
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += if c.is_ascii() { 1 } else { 4 };
+        if used > budget * 4 {
+            let cut = text[..index].rfind(char::is_whitespace).unwrap_or(index);
+            return text[..cut].trim_end();
+        }
+    }
+    text
+}
+
+// a description line worth prompting with, chapter lines keep just their name
+fn prompt_line(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.contains("://") || line.contains("www.") {
+        return None;
+    }
+    let line = match line.split_once(char::is_whitespace) {
+        Some((time, rest))
+            if time.contains(':') && time.chars().all(|c| c.is_ascii_digit() || c == ':') =>
+        {
+            rest.trim_start_matches(|c: char| c.is_whitespace() || c == '-' || c == '—')
+        }
+        _ => line,
+    };
+    (!line.is_empty()).then_some(line)
+}
+
+// what is left of the description once links are gone, keywords and the title,
+// in that order since whisper drops the front of a long prompt; the title gets
+// its tokens first, then the keywords and the description is cut to what remains
+pub fn build_prompt(
+    title: &str,
+    keywords: Option<&str>,
+    description: Option<&str>,
+) -> Option<String> {
+    let description = description
+        .unwrap_or_default()
+        .lines()
+        .filter_map(prompt_line)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut budget = MAX_PROMPT_TOKENS;
+    let mut parts = Vec::new();
+    for part in [title, keywords.unwrap_or_default(), description.as_str()] {
+        // one token for the line break
+        let part = fit_tokens(part.trim(), budget.saturating_sub(1));
+        if part.is_empty() {
+            continue;
+        }
+        budget = budget.saturating_sub(estimate_tokens(part) + 1);
+        parts.push(part);
+    }
+    parts.reverse();
+    let prompt = parts.join("\n");
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+// the language picked for the video and a prompt from what its page says
+pub fn get_context(app: &tauri::AppHandle, id: i64) -> Result<TranscribeContext, String> {
+    let (title, keywords, description, language) = db::get_context_with_id(app.state(), id)?;
+    Ok(TranscribeContext {
+        language: language.as_deref().and_then(normalize_language),
+        prompt: build_prompt(&title, keywords.as_deref(), description.as_deref()),
+    })
+}
+
+// why an attempt failed, `retryable` when another one could go better
+#[derive(Debug)]
+pub struct TranscribeError {
+    pub message: String,
+    pub retryable: bool,
+    // what a 429 asked to wait
+    pub retry_after: Option<Duration>,
+}
+
+impl TranscribeError {
+    fn retryable(message: String) -> Self {
+        Self {
+            message,
+            retryable: true,
+            retry_after: None,
+        }
+    }
+}
+
+// plain errors, e.g. a missing model or an unreadable file, fail the same way again
+impl From<String> for TranscribeError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            retryable: false,
+            retry_after: None,
+        }
+    }
+}
+
+impl fmt::Display for TranscribeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+pub trait Transcriber: Send + Sync {
+    // the segments of one audio file, with times relative to its start
+    fn transcribe<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        audio_path: &'a Path,
+        context: &'a TranscribeContext,
+    ) -> BoxFuture<'a, Result<Vec<Segment>, TranscribeError>>;
+
+    // files past this have to be split first, none when any size goes
+    fn max_upload_bytes(&self) -> Option<u64> {
+        None
+    }
+
+    // the codec audio has to be in, over the one in the settings
+    fn required_codec(&self) -> Option<SpeechCodec> {
+        None
+    }
+
+    // chunks it can take at once
+    fn max_concurrency(&self) -> usize {
+        usize::MAX
+    }
+
+    // sends `stream` events itself while transcribing, otherwise the text of
+    // each chunk is sent once it is done
+    fn streams_text(&self) -> bool {
+        false
+    }
+}
+
+// define the transcription struct with only segments in my interest. OpenAI
+// style apis give words apart from segments, the asr server inside them
+#[derive(Debug, Deserialize)]
+struct TranscriptionResponse {
+    segments: Vec<Segment>,
+    #[serde(default)]
+    words: Vec<Word>,
+}
+
+async fn audio_part(audio_path: &Path) -> Result<Part, String> {
+    let buffer = tokio::fs::read(audio_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Part::bytes(buffer)
+        .file_name(
+            audio_path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+        )
+        .mime_str(ffmpeg_audio::mime_type(audio_path))
+        .map_err(|e| e.to_string())
+}
+
+// seconds, the http date form is left to the usual backoff
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+    match retry_after {
+        Some(delay) if delay > MAX_RETRY_AFTER => None,
+        Some(delay) => Some(delay),
+        None => Some((BASE_DELAY * 2u32.pow(attempt)).min(MAX_DELAY)),
+    }
+}
+
+async fn send_transcription(
+    request: reqwest::RequestBuilder,
+) -> Result<Vec<Segment>, TranscribeError> {
+    // connection errors and timeouts are worth another try
+    let response = request
+        .send()
+        .await
+        .map_err(|e| TranscribeError::retryable(e.to_string()))?;
+    let status = response.status();
+    if status != StatusCode::OK {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(TranscribeError {
+            message: format!("API error {} - {}", status, error_text),
+            retryable: status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            retry_after,
+        });
+    }
+    let transcription = response
+        .json::<TranscriptionResponse>()
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut segments = transcription.segments;
+    attach_words(&mut segments, transcription.words);
+    Ok(segments)
+}
+
+// `/v1/audio/transcriptions` of OpenAI, Groq and anything copying them
+pub struct OpenAiTranscriber {
+    pub url: String,
+    pub model: String,
+    pub api_key: String,
+}
+
+impl OpenAiTranscriber {
+    // groq's own endpoint and model, the whisper url and model in the settings
+    // are the openai ones
+    pub fn groq(api_key: String) -> Self {
+        Self {
+            url: GROQ_URL.to_string(),
+            model: GROQ_MODEL.to_string(),
+            api_key,
+        }
+    }
+}
+
+impl Transcriber for OpenAiTranscriber {
+    fn transcribe<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        audio_path: &'a Path,
+        context: &'a TranscribeContext,
+    ) -> BoxFuture<'a, Result<Vec<Segment>, TranscribeError>> {
+        Box::pin(async move {
+            let client = create_client(app).await.map_err(|e| e.to_string())?;
+            let mut form = Form::new()
+                .text("model", self.model.clone())
+                .text("response_format", "verbose_json")
+                // asking for words drops segments unless both are asked for
+                .text("timestamp_granularities[]", "segment")
+                .text("timestamp_granularities[]", "word")
+                .part("file", audio_part(audio_path).await?);
+            if let Some(language) = &context.language {
+                form = form.text("language", language.clone());
+            }
+            if let Some(prompt) = &context.prompt {
+                form = form.text("prompt", prompt.clone());
+            }
+            let request = client
+                .post(&self.url)
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .multipart(form);
+            send_transcription(request).await
+        })
+    }
+
+    fn max_upload_bytes(&self) -> Option<u64> {
+        Some(OPENAI_MAX_UPLOAD_BYTES)
+    }
+}
+
+// the whisper.cpp sidecar with a ggml model on disk
+pub struct LocalTranscriber {
+    pub model_path: String,
+}
+
+impl Transcriber for LocalTranscriber {
+    fn transcribe<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        audio_path: &'a Path,
+        context: &'a TranscribeContext,
+    ) -> BoxFuture<'a, Result<Vec<Segment>, TranscribeError>> {
+        Box::pin(async move {
+            whisper_cpp::transcribe(app, &self.model_path, audio_path, context)
+                .await
+                .map_err(TranscribeError::from)
+        })
+    }
+
+    // whisper.cpp reads flac but not opus
+    fn required_codec(&self) -> Option<SpeechCodec> {
+        Some(SpeechCodec::Flac)
+    }
+
+    // it already uses every core
+    fn max_concurrency(&self) -> usize {
+        1
+    }
+
+    fn streams_text(&self) -> bool {
+        true
+    }
+}
+
+// a self hosted whisper-asr-webservice style server, `POST /asr` with an
+// `audio_file` field, e.g. with its faster-whisper engine
+pub struct AsrServerTranscriber {
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+impl AsrServerTranscriber {
+    fn endpoint(&self) -> String {
+        let url = self.url.trim_end_matches('/');
+        if url.ends_with("/asr") {
+            url.to_string()
+        } else {
+            format!("{}/asr", url)
+        }
+    }
+}
+
+impl Transcriber for AsrServerTranscriber {
+    fn transcribe<'a>(
+        &'a self,
+        app: &'a tauri::AppHandle,
+        audio_path: &'a Path,
+        context: &'a TranscribeContext,
+    ) -> BoxFuture<'a, Result<Vec<Segment>, TranscribeError>> {
+        Box::pin(async move {
+            let client = create_client(app).await.map_err(|e| e.to_string())?;
+            let form = Form::new().part("audio_file", audio_part(audio_path).await?);
+            let mut request = client
+                .post(self.endpoint())
+                .query(&[
+                    ("task", "transcribe"),
+                    ("output", "json"),
+                    ("encode", "true"),
+                    ("word_timestamps", "true"),
+                ])
+                .query(&[
+                    ("language", context.language.as_deref()),
+                    ("initial_prompt", context.prompt.as_deref()),
+                ])
+                .multipart(form);
+            if let Some(api_key) = &self.api_key {
+                request = request.header("Authorization", format!("Bearer {}", api_key));
+            }
+            send_transcription(request).await
+        })
+    }
+}
+
+// when requests may go out again, shared by the chunks of a run so a 429 for one
+// holds back the others instead of each running into it on its own
+#[derive(Debug, Default)]
+pub struct RetryGate(Mutex<Option<Instant>>);
+
+impl RetryGate {
+    fn deadline(&self) -> Option<Instant> {
+        *self.0.lock().unwrap()
+    }
+
+    // keep requests back until `deadline`, unless they already wait longer
+    fn hold_until(&self, deadline: Instant) {
+        let mut current = self.0.lock().unwrap();
+        if *current < Some(deadline) {
+            *current = Some(deadline);
+        }
+    }
+
+    async fn wait(&self) {
+        if let Some(deadline) = self.deadline() {
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
+// `transcribe` with up to `MAX_ATTEMPTS` tries, waiting between them as long as
+// a 429 asks or with an exponential backoff; the wait goes through `gate` so
+// every request sharing it pauses too
+pub async fn transcribe_with_retry(
+    transcriber: &dyn Transcriber,
+    app: &tauri::AppHandle,
+    audio_path: &Path,
+    context: &TranscribeContext,
+    gate: &RetryGate,
+) -> Result<Vec<Segment>, String> {
+    let mut attempt = 0;
+    loop {
+        gate.wait().await;
+        let error = match transcriber.transcribe(app, audio_path, context).await {
+            Ok(segments) => return Ok(segments),
+            Err(error) => error,
+        };
+        attempt += 1;
+        let delay = backoff_delay(attempt - 1, error.retry_after);
+        match delay {
+            Some(delay) if error.retryable && attempt < MAX_ATTEMPTS => {
+                gate.hold_until(Instant::now() + delay)
+            }
+            _ => return Err(error.message),
+        }
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+// `openai`, `groq`, `local` or `asr`. unset picks local when a model is
+// configured and openai otherwise, as before there was a choice
+pub fn from_settings(settings: &AppSettings) -> Result<Box<dyn Transcriber>, String> {
+    let local_model = non_empty(&settings.whisper_local_model);
+    let name = non_empty(&settings.transcriber)
+        .map(|name| name.to_ascii_lowercase())
+        .unwrap_or_else(|| match local_model {
+            Some(_) => "local".to_string(),
+            None => "openai".to_string(),
+        });
+    let url = non_empty(&settings.whisper_url);
+    let model = non_empty(&settings.whisper_model_name);
+    let api_key = non_empty(&settings.whisper_api_key);
+
+    match name.as_str() {
+        "openai" => match (url, model, api_key) {
+            (Some(url), Some(model), Some(api_key)) => Ok(Box::new(OpenAiTranscriber {
+                url,
+                model,
+                api_key,
+            })),
+            _ => Err("no api settings found".to_string()),
+        },
+        "groq" => Ok(Box::new(OpenAiTranscriber::groq(
+            api_key.ok_or("no groq api key found")?,
+        ))),
+        "local" => Ok(Box::new(LocalTranscriber {
+            model_path: local_model.ok_or("no local whisper model set")?,
+        })),
+        "asr" => Ok(Box::new(AsrServerTranscriber {
+            url: url.ok_or("no asr server url set")?,
+            api_key,
+        })),
+        other => Err(format!("unknown transcriber: {}", other)),
+    }
+}
+
+pub fn get_transcriber(app: &tauri::AppHandle) -> Result<Box<dyn Transcriber>, String> {
+    from_settings(&setting::get_settings(app).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_settings_works() {
+        let mut settings = AppSettings::default();
+        assert!(from_settings(&settings).is_err());
+
+        settings.whisper_api_key = Some("key".to_string());
+        settings.transcriber = Some("Groq".to_string());
+        assert_eq!(
+            from_settings(&settings).unwrap().max_upload_bytes(),
+            Some(OPENAI_MAX_UPLOAD_BYTES)
+        );
+        // the openai endpoint left in the settings is not sent to groq
+        settings.whisper_url = Some("https://api.openai.com/v1/audio/transcriptions".to_string());
+        settings.whisper_model_name = Some("whisper-1".to_string());
+        assert!(from_settings(&settings).is_ok());
+        let groq = OpenAiTranscriber::groq("key".to_string());
+        assert_eq!(groq.url, GROQ_URL);
+        assert_eq!(groq.model, GROQ_MODEL);
+        settings.whisper_url = None;
+        settings.whisper_model_name = None;
+
+        settings.transcriber = None;
+        settings.whisper_local_model = Some("/models/ggml-base.bin".to_string());
+        assert_eq!(from_settings(&settings).unwrap().max_upload_bytes(), None);
+
+        settings.transcriber = Some("asr".to_string());
+        assert!(from_settings(&settings).is_err());
+        settings.transcriber = Some("nope".to_string());
+        assert!(from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn retry_delay_works() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+
+        assert_eq!(backoff_delay(0, None), Some(BASE_DELAY));
+        assert_eq!(backoff_delay(2, None), Some(BASE_DELAY * 4));
+        assert_eq!(backoff_delay(10, None), Some(MAX_DELAY));
+        let asked = Duration::from_secs(90);
+        assert_eq!(backoff_delay(0, Some(asked)), Some(asked));
+        assert_eq!(backoff_delay(0, Some(Duration::from_secs(3600))), None);
+
+        let gate = RetryGate::default();
+        assert_eq!(gate.deadline(), None);
+        let now = Instant::now();
+        gate.hold_until(now + Duration::from_secs(5));
+        gate.hold_until(now + Duration::from_secs(1));
+        assert_eq!(gate.deadline(), Some(now + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn context_works() {
+        assert_eq!(normalize_language(" en-US "), Some("en".to_string()));
+        assert_eq!(normalize_language("ZH_tw"), Some("zh".to_string()));
+        assert_eq!(normalize_language("auto"), None);
+        assert_eq!(normalize_language(""), None);
+        assert_eq!(normalize_language("xx"), None);
+        assert_eq!(normalize_language("eng"), None);
+
+        let description = "Kubernetes with Kelsey Hightower\n\
+                           https://example.com/sponsor\n\
+                           00:00 Intro\n\
+                           01:23 - Istio";
+        assert_eq!(
+            build_prompt("Cloud Talk #12", Some("k8s kubectl"), Some(description)).unwrap(),
+            "Kubernetes with Kelsey Hightower Intro Istio\nk8s kubectl\nCloud Talk #12"
+        );
+        assert_eq!(build_prompt(" ", None, Some("www.example.com")), None);
+
+        // a long description is cut, the title at the end survives
+        let long = build_prompt("Title", None, Some(&"word ".repeat(500))).unwrap();
+        assert!(estimate_tokens(&long) <= MAX_PROMPT_TOKENS);
+        assert!(long.starts_with("word") && long.ends_with("word\nTitle"));
+        let cjk = build_prompt("标题", None, Some(&"字".repeat(500))).unwrap();
+        assert!(estimate_tokens(&cjk) <= MAX_PROMPT_TOKENS);
+        assert!(cjk.ends_with("\n标题"));
+        assert_eq!(estimate_tokens("abcdefgh 字"), 4);
+    }
+
+    #[test]
+    fn asr_endpoint_works() {
+        let server = AsrServerTranscriber {
+            url: "http://localhost:9000/".to_string(),
+            api_key: None,
+        };
+        assert_eq!(server.endpoint(), "http://localhost:9000/asr");
+    }
+}
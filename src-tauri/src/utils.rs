@@ -1,4 +1,5 @@
-use crate::whisper::Segment;
+use crate::whisper::{is_unspaced, Segment, GAP_TEXT};
+use ffmpeg_audio::SubtitleCue;
 use regex::Regex;
 use std::time::Duration;
 use tube_rs::SubtitleEntry;
@@ -35,11 +36,23 @@ pub fn transform_subtitles_to_segments(subtitles: Vec<SubtitleEntry>) -> Vec<Seg
             start: subtitle.timestamp as f64 / 1000.0,
             end: (subtitle.timestamp + subtitle.duration as u64) as f64 / 1000.0,
             text: subtitle.text,
+            words: Vec::new(),
         })
     }
     segments
 }
 
+pub fn transform_cues_to_segments(cues: Vec<SubtitleCue>) -> Vec<Segment> {
+    cues.into_iter()
+        .map(|cue| Segment {
+            start: cue.start,
+            end: cue.end,
+            text: cue.text,
+            words: Vec::new(),
+        })
+        .collect()
+}
+
 pub fn transform_segments_to_chunks(description: &str, segments: Vec<Segment>) -> Vec<String> {
     let segments: Vec<Segment> = segments
         .into_iter()
@@ -95,6 +108,57 @@ pub fn transform_segments_to_chunks(description: &str, segments: Vec<Segment>) -
     chunks
 }
 
+// `(start, end)` in seconds of every chapter listed in `content`, a description or
+// a generated summary whose detailed part repeats the timestamps, so each start
+// counts once; the last chapter runs to `duration`
+pub fn chapter_ranges(content: &str, duration: f64) -> Vec<(f64, f64)> {
+    let mut timelines = parse_timeline(content);
+    timelines.sort_by_key(|e| e.timestamp);
+    let mut starts: Vec<f64> = timelines
+        .iter()
+        .map(|e| e.timestamp.as_secs_f64())
+        .collect();
+    starts.dedup();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| (start, starts.get(i + 1).copied().unwrap_or(duration)))
+        .collect()
+}
+
+// a markdown report of a generated summary, `stills` pairs a chapter start with the
+// path of its image; each still goes under the first line with that start and the
+// ones without such a line under the title
+pub fn summary_report(title: &str, summary: &str, stills: &[(f64, String)]) -> String {
+    let image = |path: &str| format!("\n![chapter still](<{}>)\n\n", path);
+    let mut placed = vec![false; stills.len()];
+    let mut body = String::new();
+    for line in summary.lines() {
+        body.push_str(line);
+        body.push('\n');
+        let Some(entry) = parse_timeline(line).into_iter().next() else {
+            continue;
+        };
+        let start = entry.timestamp.as_secs_f64();
+        for (i, (still_start, path)) in stills.iter().enumerate() {
+            if !placed[i] && (still_start - start).abs() < 0.5 {
+                placed[i] = true;
+                body.push_str(&image(path));
+            }
+        }
+    }
+
+    let mut report = format!("# {}\n", title);
+    for (i, (_, path)) in stills.iter().enumerate() {
+        if !placed[i] {
+            report.push_str(&image(path));
+        }
+    }
+    report.push('\n');
+    report.push_str(&body);
+    report
+}
+
 fn convert_seconds_to_time(seconds: u64) -> String {
     let minutes = seconds / 60;
     let seconds = seconds % 60;
@@ -116,6 +180,102 @@ pub fn transform_segment_to_string(segments: Vec<Segment>) -> String {
     content
 }
 
+// longest cue made from word timings, a segment is split past either
+const MAX_CUE_WORDS: usize = 12;
+const MAX_CUE_SECONDS: f64 = 6.0;
+
+// (start, end, text) of each subtitle cue. segments with words are cut into
+// shorter cues at sentence ends or the limits above, and timed from the words
+// spoken rather than the padded segment. gaps are left out
+fn subtitle_cues(segments: &[Segment]) -> Vec<(f64, f64, String)> {
+    let mut cues = Vec::new();
+    for segment in segments {
+        let text = segment.text.trim();
+        if text.is_empty() || text == GAP_TEXT {
+            continue;
+        }
+        if segment.words.is_empty() {
+            cues.push((segment.start, segment.end, text.to_string()));
+            continue;
+        }
+        // built from the raw words; local whisper puts the space in front of each
+        // word, the openai and groq apis send bare words that need one between
+        // them unless both sides are of a script written without spaces
+        let mut cue = String::new();
+        let mut cue_words = 0;
+        let (mut cue_start, mut cue_end) = (0.0, 0.0);
+        for word in &segment.words {
+            let spoken = word.word.trim();
+            if spoken.is_empty() {
+                continue;
+            }
+            if cue_words == 0 {
+                cue_start = word.start;
+            }
+            let spaced = word.word.starts_with(char::is_whitespace)
+                || cue.ends_with(char::is_whitespace)
+                || (cue.ends_with(is_unspaced) && word.word.starts_with(is_unspaced));
+            if !cue.is_empty() && !spaced {
+                cue.push(' ');
+            }
+            cue.push_str(&word.word);
+            cue_words += 1;
+            cue_end = word.end;
+            let sentence_end = spoken.ends_with(['.', '?', '!', '。', '？', '！']);
+            if sentence_end || cue_words >= MAX_CUE_WORDS || word.end - cue_start >= MAX_CUE_SECONDS
+            {
+                cues.push((cue_start, cue_end, cue.trim().to_string()));
+                cue.clear();
+                cue_words = 0;
+            }
+        }
+        if cue_words > 0 {
+            cues.push((cue_start, cue_end, cue.trim().to_string()));
+        }
+    }
+    cues
+}
+
+// `01:02:03,456`, with a `.` before the milliseconds for vtt
+fn subtitle_timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+pub fn segments_to_srt(segments: &[Segment]) -> String {
+    let mut content = String::new();
+    for (index, (start, end, text)) in subtitle_cues(segments).into_iter().enumerate() {
+        content.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            subtitle_timestamp(start, ','),
+            subtitle_timestamp(end, ','),
+            text
+        ));
+    }
+    content
+}
+
+pub fn segments_to_vtt(segments: &[Segment]) -> String {
+    let mut content = String::from("WEBVTT\n\n");
+    for (start, end, text) in subtitle_cues(segments) {
+        content.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            subtitle_timestamp(start, '.'),
+            subtitle_timestamp(end, '.'),
+            text
+        ));
+    }
+    content
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,6 +310,119 @@ mod tests {
         assert_eq!(result.len(), 3);
     }
 
+    #[test]
+    fn test_chapter_ranges() {
+        let input = r#"00:00 Intro
+01:23 - Guest
+02:45 — Outro"#;
+        let chapters = chapter_ranges(input, 200.0);
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[0], (0.0, 83.0));
+        assert_eq!(chapters[2], (165.0, 200.0));
+        assert!(chapter_ranges("no chapters", 200.0).is_empty());
+
+        let summary = "1. 00:00 - Intro\n2. 01:23 - Guest\n1. 00:00 - Longer intro";
+        assert_eq!(
+            chapter_ranges(summary, 200.0),
+            vec![(0.0, 83.0), (83.0, 200.0)]
+        );
+    }
+
+    #[test]
+    fn test_summary_report() {
+        let summary = "## Short Summary:\n1. 00:00 - Intro\n2. 01:23 - Guest\n## Detailed:\n1. 00:00 - Longer intro";
+        let stills = vec![
+            (0.0, "talk_files/frame_001.jpg".to_string()),
+            (83.0, "talk_files/frame_002.jpg".to_string()),
+            (300.0, "talk_files/frame_003.jpg".to_string()),
+        ];
+        assert_eq!(
+            summary_report("Talk", summary, &stills),
+            "# Talk\n\n![chapter still](<talk_files/frame_003.jpg>)\n\n\n## Short Summary:\n1. 00:00 - Intro\n\n![chapter still](<talk_files/frame_001.jpg>)\n\n2. 01:23 - Guest\n\n![chapter still](<talk_files/frame_002.jpg>)\n\n## Detailed:\n1. 00:00 - Longer intro\n"
+        );
+    }
+
+    #[test]
+    fn test_subtitles() {
+        use crate::whisper::Word;
+
+        let word = |word: &str, start: f64, end: f64| Word {
+            word: word.to_string(),
+            start,
+            end,
+        };
+        let segments = vec![
+            Segment {
+                start: 0.0,
+                end: 5.0,
+                text: " Hi there. Bye".to_string(),
+                words: vec![
+                    word(" Hi", 0.4, 0.8),
+                    word(" there.", 0.8, 1.2),
+                    word(" Bye", 3.0, 3.5),
+                ],
+            },
+            Segment {
+                start: 5.0,
+                end: 3725.5,
+                text: GAP_TEXT.to_string(),
+                words: Vec::new(),
+            },
+            Segment {
+                start: 3725.5,
+                end: 3727.0,
+                text: " No words".to_string(),
+                words: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            segments_to_srt(&segments),
+            "1\n00:00:00,400 --> 00:00:01,200\nHi there.\n\n\
+             2\n00:00:03,000 --> 00:00:03,500\nBye\n\n\
+             3\n01:02:05,500 --> 01:02:07,000\nNo words\n\n"
+        );
+        assert!(segments_to_vtt(&segments)
+            .starts_with("WEBVTT\n\n00:00:00.400 --> 00:00:01.200\nHi there.\n\n"));
+
+        // the openai and groq apis send bare words
+        let segments = vec![Segment {
+            start: 0.0,
+            end: 5.0,
+            text: "Hi there. Bye now, 你好".to_string(),
+            words: vec![
+                word("Hi", 0.4, 0.8),
+                word("there.", 0.8, 1.2),
+                word("Bye", 3.0, 3.5),
+                word("now,", 3.5, 3.8),
+                word("你", 3.8, 4.0),
+                word("好", 4.0, 4.2),
+            ],
+        }];
+        assert_eq!(
+            segments_to_srt(&segments),
+            "1\n00:00:00,400 --> 00:00:01,200\nHi there.\n\n\
+             2\n00:00:03,000 --> 00:00:04,200\nBye now, 你好\n\n"
+        );
+
+        // no spaces are put between words of unspaced scripts
+        let segments = vec![Segment {
+            start: 0.0,
+            end: 2.0,
+            text: "你好。再见".to_string(),
+            words: vec![
+                word("你好", 0.0, 0.5),
+                word("。", 0.5, 0.6),
+                word("再见", 1.0, 1.5),
+                word(" ", 1.5, 1.6),
+            ],
+        }];
+        assert_eq!(
+            segments_to_srt(&segments),
+            "1\n00:00:00,000 --> 00:00:00,600\n你好。\n\n\
+             2\n00:00:01,000 --> 00:00:01,500\n再见\n\n"
+        );
+    }
+
     #[test]
     fn test_invalid_input() {
         let input = "Invalid timestamp";
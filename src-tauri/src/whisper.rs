@@ -1,28 +1,21 @@
 use anyhow::Result;
 use futures_util::StreamExt;
-use reqwest::multipart::{Form, Part};
-use reqwest::{Client, Proxy, StatusCode};
+use reqwest::{Client, Proxy};
 use serde::{Deserialize, Serialize};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tauri::Manager;
 use tauri::{Emitter, State};
-use tokio::fs::{self, File};
-use tokio::io::AsyncReadExt;
+use tokio::fs;
 
 use crate::gemini::parse_gemini;
+use ffmpeg_audio::{ChunkInfo, SpeechRegion, TimeMap};
 
 use super::db::{self, DataBase};
 use super::setting;
+use super::transcriber::{self, TranscribeContext, Transcriber};
 use super::utils;
 
-// define the transcription struct with only text in my interest
-#[derive(Debug, Deserialize)]
-struct TranscriptionResponse {
-    text: String,
-    segments: Vec<Segment>,
-}
-
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct GeminiRequest {
     model: String,
@@ -101,16 +94,234 @@ pub async fn create_client(app: &tauri::AppHandle) -> Result<Client> {
 // System Prompt: summarize with mindmap?
 //
 //
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Segment {
     pub start: f64,
     pub end: f64,
     pub text: String,
+    // empty when the transcriber has no word timings, e.g. youtube captions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
 }
 
-//
-//
-//
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Word {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Segment {
+    // move the segment and its words, e.g. from a chunk onto the whole audio
+    pub fn map_times(&mut self, map: impl Fn(f64) -> f64) {
+        self.start = map(self.start);
+        self.end = map(self.end);
+        for word in self.words.iter_mut() {
+            word.start = map(word.start);
+            word.end = map(word.end);
+        }
+    }
+}
+
+// hand out `words`, timed over the whole file, to the segments they fall in
+pub fn attach_words(segments: &mut [Segment], words: Vec<Word>) {
+    for word in words {
+        let middle = (word.start + word.end) / 2.0;
+        let index = segments
+            .partition_point(|segment| segment.end <= middle)
+            .min(segments.len().saturating_sub(1));
+        if let Some(segment) = segments.get_mut(index) {
+            segment.words.push(word);
+        }
+    }
+}
+
+// stands in the stored transcript for audio whose chunk failed every attempt,
+// replaced by `retry_missing_chunks`
+pub const GAP_TEXT: &str = "[untranscribed audio]";
+
+// a chunk that failed every attempt, times are on the uploaded audio's timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedChunk {
+    pub index: usize,
+    pub start: f64,
+    pub end: f64,
+    pub path: PathBuf,
+    pub error: String,
+}
+
+// words both chunks must agree on before the overlap is trusted as aligned
+const MIN_OVERLAP_MATCH: usize = 2;
+
+fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// scripts written without spaces between words, chinese, japanese and thai
+pub(crate) fn is_unspaced(c: char) -> bool {
+    matches!(c,
+        '\u{0E00}'..='\u{0EFF}'
+        | '\u{3000}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+// byte ranges of the words of `text`, every character of a script written
+// without spaces counts as a word of its own
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() || is_unspaced(c) {
+            if let Some(start) = start.take() {
+                spans.push((start, i));
+            }
+            if is_unspaced(c) {
+                spans.push((i, i + c.len_utf8()));
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        spans.push((start, text.len()));
+    }
+    spans
+}
+
+// (segment index, word index, normalized word) for every word of `segments`
+fn segment_words(segments: &[Segment]) -> Vec<(usize, usize, String)> {
+    segments
+        .iter()
+        .enumerate()
+        .flat_map(|(i, segment)| {
+            word_spans(&segment.text)
+                .into_iter()
+                .enumerate()
+                .map(move |(j, (from, to))| (i, j, normalize_word(&segment.text[from..to])))
+        })
+        .filter(|(_, _, word)| !word.is_empty())
+        .collect()
+}
+
+// longest run of equal words, returns (start in a, start in b, length)
+fn longest_common_run(a: &[&str], b: &[&str]) -> Option<(usize, usize, usize)> {
+    let mut best: Option<(usize, usize, usize)> = None;
+    let mut previous = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        let mut current = vec![0; b.len() + 1];
+        for j in 1..=b.len() {
+            if a[i - 1] == b[j - 1] {
+                current[j] = previous[j - 1] + 1;
+                if best.is_none_or(|(_, _, len)| current[j] > len) {
+                    best = Some((i - current[j], j - current[j], current[j]));
+                }
+            }
+        }
+        previous = current;
+    }
+    best
+}
+
+// keep the words `range` of a segment, with their own timing when every word
+// of the text has one, interpolated otherwise. the text keeps its spacing and
+// the leading space segments start with
+fn slice_segment(segment: &Segment, range: std::ops::Range<usize>) -> Segment {
+    let words = word_spans(&segment.text);
+    let range = range.start.min(words.len())..range.end.min(words.len());
+    let text = if range.is_empty() {
+        String::new()
+    } else {
+        let lead = if segment.text.starts_with(char::is_whitespace) {
+            " "
+        } else {
+            ""
+        };
+        let text = &segment.text[words[range.start].0..words[range.end - 1].1];
+        format!("{}{}", lead, text)
+    };
+    if segment.words.len() == words.len() && !range.is_empty() {
+        let timed = segment.words[range].to_vec();
+        return Segment {
+            start: timed[0].start,
+            end: timed[timed.len() - 1].end,
+            text,
+            words: timed,
+        };
+    }
+
+    let total = words.len().max(1) as f64;
+    let span = segment.end - segment.start;
+    let start = segment.start + span * range.start as f64 / total;
+    let end = segment.start + span * range.end as f64 / total;
+    Segment {
+        start,
+        end,
+        text,
+        words: segment
+            .words
+            .iter()
+            .filter(|word| word.start >= start && word.end <= end)
+            .cloned()
+            .collect(),
+    }
+}
+
+// join the transcript of the next chunk onto `prev`, where both cover
+// `overlap` seconds, dropping whatever was transcribed twice
+fn stitch_segments(prev: Vec<Segment>, next: Vec<Segment>, overlap: (f64, f64)) -> Vec<Segment> {
+    let (overlap_start, overlap_end) = overlap;
+    if overlap_end <= overlap_start || prev.is_empty() {
+        return prev.into_iter().chain(next).collect();
+    }
+
+    let tail_from = prev
+        .iter()
+        .position(|segment| segment.end > overlap_start)
+        .unwrap_or(prev.len());
+    let head_to = next
+        .iter()
+        .position(|segment| segment.start >= overlap_end)
+        .unwrap_or(next.len());
+    let tail_words = segment_words(&prev[tail_from..]);
+    let head_words = segment_words(&next[..head_to]);
+
+    let tail_keys: Vec<&str> = tail_words.iter().map(|(_, _, w)| w.as_str()).collect();
+    let head_keys: Vec<&str> = head_words.iter().map(|(_, _, w)| w.as_str()).collect();
+
+    match longest_common_run(&tail_keys, &head_keys) {
+        Some((a, b, len)) if len >= MIN_OVERLAP_MATCH => {
+            let (prev_segment, prev_word, _) = tail_words[a];
+            let (next_segment, next_word, _) = head_words[b];
+            let prev_segment = tail_from + prev_segment;
+
+            let head = slice_segment(&next[next_segment], next_word..usize::MAX);
+            let mut segments = prev[..prev_segment].to_vec();
+            if prev_word > 0 {
+                let mut tail = slice_segment(&prev[prev_segment], 0..prev_word);
+                tail.end = tail.end.min(head.start).max(tail.start);
+                segments.push(tail);
+            }
+            segments.push(head);
+            segments.extend_from_slice(&next[next_segment + 1..]);
+            segments
+        }
+        _ => {
+            // nothing to align on, cut both transcripts in the middle of the overlap
+            let middle = (overlap_start + overlap_end) / 2.0;
+            prev.into_iter()
+                .filter(|segment| segment.start < middle)
+                .chain(next.into_iter().filter(|segment| segment.start >= middle))
+                .collect()
+        }
+    }
+}
 
 fn get_system_prompt(language: &str) -> String {
     let prompt = match language {
@@ -507,96 +718,397 @@ async fn handle_open_api(
     Ok(summary.join(""))
 }
 
-async fn transcribe_audio(
+// the transcript of `chunks` with a `GAP_TEXT` segment for every chunk that
+// could not be transcribed, which come back as well even when that is all of them
+pub async fn trancript(
     app: &tauri::AppHandle,
-    api_key: &str,
-    audio_path: &str,
-    audio_model: &str,
-    api_url: &str,
-) -> Result<TranscriptionResponse> {
-    let client = create_client(app).await?;
-    let path = Path::new(audio_path);
-    let mut file = File::open(path).await?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).await?;
-
-    let file_part = Part::bytes(buffer)
-        .file_name(path.file_name().unwrap().to_string_lossy().to_string())
-        .mime_str("audio/wav")?;
-    let form = Form::new()
-        .text("model", audio_model.to_string())
-        .text("response_format", "verbose_json")
-        .part("file", file_part);
+    transcriber: &dyn Transcriber,
+    chunks: &[ChunkInfo],
+    time_map: Option<&TimeMap>,
+    context: &TranscribeContext,
+) -> Result<(Vec<Segment>, Vec<FailedChunk>), String> {
+    let mut chunks = chunks.to_vec();
+    chunks.sort_by_key(|chunk| chunk.index);
+    let limit = setting::get_concurrency(app)
+        .min(transcriber.max_concurrency())
+        .max(1);
 
-    let response = client
-        .post(api_url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .multipart(form)
-        .send()
-        .await?;
+    // `buffered` runs up to `limit` requests but yields in chunk order, so the
+    // stitching below always sees neighbouring chunks
+    let gate = transcriber::RetryGate::default();
+    let gate = &gate;
+    let mut transcribed = futures_util::stream::iter(&chunks)
+        .map(|chunk| async move {
+            let result =
+                transcriber::transcribe_with_retry(transcriber, app, &chunk.path, context, gate)
+                    .await;
+            (chunk, result)
+        })
+        .buffered(limit);
+    let mut segments = Vec::new();
+    let mut failed = Vec::new();
+    let mut prev_end = None;
+    while let Some((chunk, result)) = transcribed.next().await {
+        match result {
+            Ok(mut chunk_segments) => {
+                if !transcriber.streams_text() {
+                    let text: String = chunk_segments.iter().map(|s| s.text.as_str()).collect();
+                    app.emit("stream", text).map_err(|e| e.to_string())?;
+                }
+                for segment in chunk_segments.iter_mut() {
+                    segment.map_times(|time| time + chunk.start);
+                }
+                segments = match prev_end {
+                    Some(end) => stitch_segments(segments, chunk_segments, (chunk.start, end)),
+                    None => chunk_segments,
+                };
+            }
+            Err(error) => {
+                // whatever the previous chunk covered of the overlap stays
+                segments.push(Segment {
+                    start: prev_end.unwrap_or(chunk.start).max(chunk.start),
+                    end: chunk.end(),
+                    text: GAP_TEXT.to_string(),
+                    words: Vec::new(),
+                });
+                failed.push(FailedChunk {
+                    index: chunk.index,
+                    start: chunk.start,
+                    end: chunk.end(),
+                    path: chunk.path.clone(),
+                    error,
+                });
+            }
+        };
+        prev_end = Some(chunk.end());
+    }
 
-    let status = response.status();
-    match status {
-        StatusCode::OK => {
-            let transcription = response.json::<TranscriptionResponse>().await?;
-            Ok(transcription)
-        }
-        _ => {
-            let error_text = response.text().await?;
-            anyhow::bail!("API error {} - {}", status, error_text);
+    // the chunks were cut from speech only or sped up audio, move back onto the video's timeline
+    if let Some(time_map) = time_map {
+        for segment in segments.iter_mut() {
+            segment.map_times(|time| time_map.to_original(time));
         }
     }
+
+    Ok((segments, failed))
 }
 
-pub async fn trancript(app: &tauri::AppHandle, audio_path: &Path) -> Result<Vec<Segment>, String> {
-    let settings_value = setting::get_settings(app);
+const MISSING_RECORD: &str = "missing.json";
 
-    let (api_url, model_name, api_key) = match settings_value {
-        Some(setting::AppSettings {
-            whisper_url: Some(whisper_url),
-            whisper_model_name: Some(whisper_model_name),
-            whisper_api_key: Some(api_key),
-            ..
-        }) => (whisper_url, whisper_model_name, api_key),
-        _ => return Err("no api settings found".to_string()),
-    };
+// the failed chunks of a video kept for `retry_missing_chunks`, with the time
+// map of that run to put retried segments onto the video's timeline
+#[derive(Debug, Serialize, Deserialize)]
+struct MissingChunks {
+    regions: Vec<(f64, f64)>,
+    tempo: f64,
+    chunks: Vec<FailedChunk>,
+}
+
+impl MissingChunks {
+    fn time_map(&self) -> TimeMap {
+        let regions: Vec<SpeechRegion> = self
+            .regions
+            .iter()
+            .map(|&(start, end)| SpeechRegion { start, end })
+            .collect();
+        TimeMap::new(&regions).with_tempo(self.tempo)
+    }
+}
 
+fn missing_dir(app: &tauri::AppHandle, video_id: &str) -> PathBuf {
+    app.path()
+        .cache_dir()
+        .unwrap()
+        .join("newscenter")
+        .join("missing")
+        .join(video_id)
+}
+
+// copy the audio of `failed` out of the chunk folder before it is cleared, a
+// run without failures drops what an earlier one kept
+pub async fn keep_failed_chunks(
+    app: &tauri::AppHandle,
+    video_id: &str,
+    failed: &[FailedChunk],
+    time_map: Option<&TimeMap>,
+) -> Result<()> {
+    let dir = missing_dir(app, video_id);
+    if dir.is_dir() {
+        fs::remove_dir_all(&dir).await?;
+    }
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir).await?;
     let mut chunks = Vec::new();
+    for chunk in failed {
+        let kept = dir.join(format!(
+            "{:03}_{}",
+            chunk.index + 1,
+            chunk.path.file_name().unwrap().to_string_lossy()
+        ));
+        fs::copy(&chunk.path, &kept).await?;
+        chunks.push(FailedChunk {
+            path: kept,
+            ..chunk.clone()
+        });
+    }
+    let time_map = time_map.cloned().unwrap_or_default();
+    let record = MissingChunks {
+        regions: time_map
+            .regions()
+            .iter()
+            .map(|region| (region.start, region.end))
+            .collect(),
+        tempo: time_map.tempo(),
+        chunks,
+    };
+    fs::write(dir.join(MISSING_RECORD), serde_json::to_string(&record)?).await?;
+    Ok(())
+}
 
-    if !audio_path.is_dir() {
-        let audio_path_str = audio_path.to_str().unwrap();
-        match transcribe_audio(app, &api_key, audio_path_str, &model_name, &api_url).await {
-            Ok(response) => {
-                app.emit("stream", response.text.clone()).unwrap();
-                chunks.extend(response.segments);
-            }
-            Err(e) => return Err(e.to_string()),
-        };
-    } else {
-        let mut dir = fs::read_dir(audio_path).await.map_err(|e| e.to_string())?;
-
-        let mut end_time = 0.0;
-        while let Some(entry) = dir.next_entry().await.map_err(|e| e.to_string())? {
-            let audio_path = entry.path();
-            let audio_path_str = audio_path.to_str().unwrap();
-            match transcribe_audio(app, &api_key, audio_path_str, &model_name, &api_url).await {
-                Ok(response) => {
-                    app.emit("stream", response.text.clone()).unwrap();
-                    let mut current_end = 0.0;
-                    for mut segment in response.segments {
-                        segment.start += end_time;
-                        current_end = segment.end;
-                        chunks.push(segment);
-                    }
-                    end_time = current_end;
+// put `retried`, the transcript of a failed chunk covering `range` on the video's
+// timeline, in place of its gap and stitch it to both neighbours
+fn fill_gap(segments: Vec<Segment>, retried: Vec<Segment>, range: (f64, f64)) -> Vec<Segment> {
+    let (start, end) = range;
+    let gap = segments
+        .iter()
+        .position(|segment| segment.text == GAP_TEXT && segment.start < end && segment.end > start);
+    let mut before = segments;
+    let (after, before_end) = match gap {
+        Some(gap) => {
+            let after = before.split_off(gap + 1);
+            (after, before.pop().unwrap().start)
+        }
+        // nothing marks the range, e.g. no transcript was stored because every
+        // chunk failed, so it goes in by time
+        None => {
+            let at = before
+                .iter()
+                .position(|segment| segment.start >= start)
+                .unwrap_or(before.len());
+            let after = before.split_off(at);
+            let before_end = before
+                .last()
+                .map_or(start, |segment| segment.end.max(start));
+            (after, before_end)
+        }
+    };
+
+    let joined = stitch_segments(before, retried, (start, before_end));
+    let after_start = after.first().map_or(end, |segment| segment.start);
+    stitch_segments(joined, after, (after_start, end))
+}
+
+// transcribe the chunks kept by an earlier run again and fill their gaps in the
+// stored transcript, returns how many are still missing
+#[tauri::command(rename_all = "snake_case")]
+pub async fn retry_missing_chunks(app: tauri::AppHandle, input_id: i64) -> Result<usize, String> {
+    let (video_id, _) = db::get_source_with_id(app.state(), input_id)?;
+    let dir = missing_dir(&app, &video_id);
+    let Ok(json) = fs::read_to_string(dir.join(MISSING_RECORD)).await else {
+        return Ok(0);
+    };
+    let record: MissingChunks = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let transcriber = transcriber::get_transcriber(&app)?;
+    let context = transcriber::get_context(&app, input_id)?;
+    let time_map = record.time_map();
+    let transcripts = db::get_transcripts_with_id(app.state(), input_id)?.unwrap_or_default();
+    let mut segments: Vec<Segment> = serde_json::from_str(&transcripts).unwrap_or_default();
+
+    let gate = transcriber::RetryGate::default();
+    let mut still_missing = Vec::new();
+    for chunk in record.chunks {
+        let result = transcriber::transcribe_with_retry(
+            transcriber.as_ref(),
+            &app,
+            &chunk.path,
+            &context,
+            &gate,
+        )
+        .await;
+        match result {
+            Ok(mut retried) => {
+                for segment in retried.iter_mut() {
+                    segment.map_times(|time| time_map.to_original(time + chunk.start));
                 }
-                Err(_) => continue,
-            };
+                let range = (
+                    time_map.to_original(chunk.start),
+                    time_map.to_original(chunk.end),
+                );
+                segments = fill_gap(segments, retried, range);
+                let _ = fs::remove_file(&chunk.path).await;
+            }
+            Err(error) => still_missing.push(FailedChunk { error, ..chunk }),
         }
-        remove_files_from_directory(audio_path)
+    }
+
+    let transcripts = serde_json::to_string(&segments).unwrap();
+    db::update_video(
+        app.state(),
+        input_id,
+        "transcripts".to_string(),
+        transcripts,
+    )?;
+    let missing = still_missing.len();
+    if still_missing.is_empty() {
+        fs::remove_dir_all(&dir).await.map_err(|e| e.to_string())?;
+    } else {
+        let record = MissingChunks {
+            chunks: still_missing,
+            ..record
+        };
+        let json = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+        fs::write(dir.join(MISSING_RECORD), json)
             .await
             .map_err(|e| e.to_string())?;
     }
+    app.emit("state", "update video")
+        .map_err(|e| e.to_string())?;
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, end: f64, text: &str) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+            words: Vec::new(),
+        }
+    }
 
-    Ok(chunks)
+    fn word(start: f64, end: f64, text: &str) -> Word {
+        Word {
+            word: text.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn longest_common_run_works() {
+        let a = vec!["the", "quick", "brown", "fox", "jumps"];
+        let b = vec!["brown", "fox", "jumps", "over"];
+        assert_eq!(longest_common_run(&a, &b), Some((2, 0, 3)));
+        assert_eq!(longest_common_run(&a, &["lazy", "dog"]), None);
+    }
+
+    #[test]
+    fn stitch_removes_duplicated_words() {
+        // chunk one ends at 62s, chunk two starts at 58s
+        let prev = vec![
+            segment(50.0, 56.0, " We went to the store"),
+            segment(56.0, 62.0, " and bought some fresh bre"),
+        ];
+        let next = vec![
+            segment(58.0, 61.0, " some fresh bread for dinner."),
+            segment(61.0, 65.0, " Then we went home."),
+        ];
+        let stitched = stitch_segments(prev, next, (58.0, 62.0));
+        let text: Vec<&str> = stitched.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "We went to the store",
+                "and bought",
+                "some fresh bread for dinner.",
+                "Then we went home."
+            ]
+        );
+        assert_eq!(stitched[1].start, 56.0);
+        assert_eq!(stitched[1].end, 58.0);
+        for pair in stitched.windows(2) {
+            assert!(pair[0].end <= pair[1].start + 1e-9);
+        }
+    }
+
+    #[test]
+    fn stitch_aligns_unspaced_scripts() {
+        let prev = vec![segment(50.0, 62.0, " 我们去商店买了新鲜的面")];
+        let next = vec![segment(58.0, 61.0, " 新鲜的面包当晚餐。")];
+        let stitched = stitch_segments(prev, next, (58.0, 62.0));
+        let text: Vec<&str> = stitched.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(text, vec![" 我们去商店买了", " 新鲜的面包当晚餐。"]);
+    }
+
+    #[test]
+    fn stitch_falls_back_to_middle_of_overlap() {
+        let prev = vec![segment(0.0, 5.0, " hello"), segment(5.0, 10.0, " 你好")];
+        let next = vec![segment(8.0, 9.0, " 世界"), segment(9.0, 12.0, " again")];
+        let stitched = stitch_segments(prev, next, (7.0, 10.0));
+        let text: Vec<&str> = stitched.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(text, vec!["hello", "你好", "again"]);
+    }
+
+    #[test]
+    fn stitch_without_overlap_concatenates() {
+        let prev = vec![segment(0.0, 5.0, " a")];
+        let next = vec![segment(5.0, 9.0, " b")];
+        assert_eq!(stitch_segments(prev, next, (5.0, 5.0)).len(), 2);
+    }
+
+    #[test]
+    fn attach_words_works() {
+        let mut segments = vec![segment(0.0, 2.0, " one two"), segment(2.0, 4.0, " three")];
+        attach_words(
+            &mut segments,
+            vec![
+                word(0.0, 0.9, " one"),
+                word(1.0, 2.1, " two"),
+                word(2.2, 3.0, " three"),
+                word(4.0, 4.5, " trailing"),
+            ],
+        );
+        assert_eq!(segments[0].words.len(), 2);
+        assert_eq!(segments[1].words.len(), 2);
+    }
+
+    #[test]
+    fn slice_segment_uses_word_timings() {
+        let mut timed = segment(0.0, 10.0, " we went home");
+        timed.words = vec![
+            word(0.5, 1.0, " we"),
+            word(1.0, 1.5, " went"),
+            word(6.0, 9.0, " home"),
+        ];
+        let sliced = slice_segment(&timed, 1..usize::MAX);
+        assert_eq!(sliced.text, " went home");
+        assert_eq!((sliced.start, sliced.end), (1.0, 9.0));
+        assert_eq!(sliced.words.len(), 2);
+
+        let plain = slice_segment(&segment(0.0, 9.0, " a b c"), 0..1);
+        assert_eq!((plain.start, plain.end), (0.0, 3.0));
+    }
+
+    #[test]
+    fn fill_gap_replaces_the_gap() {
+        let segments = vec![
+            segment(0.0, 10.0, " hello"),
+            segment(10.0, 20.0, GAP_TEXT),
+            segment(21.0, 30.0, " bye"),
+        ];
+        let retried = vec![
+            segment(10.0, 15.0, " middle part"),
+            segment(15.0, 20.0, " of it"),
+        ];
+        let filled = fill_gap(segments.clone(), retried.clone(), (8.0, 20.0));
+        let text: Vec<&str> = filled.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(text, vec!["hello", "middle part", "of it", "bye"]);
+
+        // nothing marks the range, the retried text goes in by time
+        let late = vec![segment(40.0, 45.0, " late")];
+        let filled = fill_gap(segments, late, (40.0, 50.0));
+        assert_eq!(filled.len(), 4);
+        assert_eq!(filled[3].text, " late");
+
+        // every chunk failed and no transcript was stored
+        let filled = fill_gap(Vec::new(), retried, (8.0, 20.0));
+        let text: Vec<&str> = filled.iter().map(|s| s.text.trim()).collect();
+        assert_eq!(text, vec!["middle part", "of it"]);
+    }
 }
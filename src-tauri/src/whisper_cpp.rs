@@ -0,0 +1,252 @@
+// transcription on the local cpu with the whisper.cpp cli, shipped as the
+// `whisper` sidecar next to `ytdown`
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::ShellExt;
+
+use super::transcriber::TranscribeContext;
+use super::whisper::{Segment, Word};
+
+// the parts of `-ojf` output in my interest
+#[derive(Debug, Deserialize)]
+struct CppOutput {
+    transcription: Vec<CppSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CppSegment {
+    // milliseconds
+    offsets: CppOffsets,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<CppToken>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CppToken {
+    text: String,
+    offsets: CppOffsets,
+}
+
+#[derive(Debug, Deserialize)]
+struct CppOffsets {
+    from: u64,
+    to: u64,
+}
+
+fn parse_output(json: &str) -> Result<Vec<Segment>, serde_json::Error> {
+    let output: CppOutput = serde_json::from_str(json)?;
+    Ok(output
+        .transcription
+        .into_iter()
+        .map(|segment| Segment {
+            start: segment.offsets.from as f64 / 1000.0,
+            end: segment.offsets.to as f64 / 1000.0,
+            words: merge_tokens(&segment.tokens),
+            text: segment.text,
+        })
+        .collect())
+}
+
+// whisper.cpp times tokens, not words. a token starting with a space starts a
+// new word, the rest are glued onto the previous one, and special tokens like
+// `[_BEG_]` or `[_TT_150]` are dropped
+fn merge_tokens(tokens: &[CppToken]) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    for token in tokens {
+        if token.text.starts_with("[_") || token.text.starts_with("<|") {
+            continue;
+        }
+        let start = token.offsets.from as f64 / 1000.0;
+        let end = token.offsets.to as f64 / 1000.0;
+        match words.last_mut() {
+            Some(word) if !token.text.starts_with(' ') => {
+                word.word.push_str(&token.text);
+                word.end = end;
+            }
+            _ => words.push(Word {
+                word: token.text.clone(),
+                start,
+                end,
+            }),
+        }
+    }
+    words
+}
+
+// `whisper_print_progress_callback: progress =  45%` on stderr with `-pp`
+fn parse_progress(line: &str) -> Option<f64> {
+    let (_, percent) = line.split_once("progress =")?;
+    percent.trim().trim_end_matches('%').parse().ok()
+}
+
+// `[00:00:01.000 --> 00:00:04.000]   some text` on stdout for every decoded segment
+fn parse_segment_line(line: &str) -> Option<&str> {
+    let (timestamps, text) = line.trim_start().strip_prefix('[')?.split_once(']')?;
+    timestamps.contains("-->").then_some(text)
+}
+
+// run `model_path`, a ggml model, over `audio_path`, which has to be wav, flac,
+// mp3 or vorbis. segments go out as `stream` events while they are decoded and
+// the percent done as `transcribe_progress`
+pub async fn transcribe(
+    app: &tauri::AppHandle,
+    model_path: &str,
+    audio_path: &Path,
+    context: &TranscribeContext,
+) -> Result<Vec<Segment>, String> {
+    if !Path::new(model_path).is_file() {
+        return Err(format!("whisper model not found: {}", model_path));
+    }
+    // whisper.cpp appends `.json` to this
+    let output_prefix = audio_path.with_extension("");
+    let json_path = PathBuf::from(format!("{}.json", output_prefix.display()));
+    let mut args = vec![
+        "-m".to_string(),
+        model_path.to_string(),
+        "-f".to_string(),
+        audio_path.to_string_lossy().to_string(),
+        "-l".to_string(),
+        context
+            .language
+            .clone()
+            .unwrap_or_else(|| "auto".to_string()),
+        // full json, with the timing of every token
+        "-ojf".to_string(),
+        "-of".to_string(),
+        output_prefix.to_string_lossy().to_string(),
+        "-pp".to_string(),
+    ];
+    if let Some(prompt) = &context.prompt {
+        args.push("--prompt".to_string());
+        args.push(prompt.clone());
+    }
+
+    let (mut events, _child) = app
+        .shell()
+        .sidecar("whisper")
+        .map_err(|e| e.to_string())?
+        .args(args)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let mut last_error = String::new();
+    while let Some(event) = events.recv().await {
+        match event {
+            CommandEvent::Stdout(line) => {
+                let line = String::from_utf8_lossy(&line);
+                if let Some(text) = parse_segment_line(&line) {
+                    app.emit("stream", text.trim_end().to_string())
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            CommandEvent::Stderr(line) => {
+                let line = String::from_utf8_lossy(&line);
+                match parse_progress(&line) {
+                    Some(percent) => app
+                        .emit("transcribe_progress", percent)
+                        .map_err(|e| e.to_string())?,
+                    None if !line.trim().is_empty() => last_error = line.trim().to_string(),
+                    None => {}
+                }
+            }
+            CommandEvent::Error(e) => return Err(e),
+            CommandEvent::Terminated(payload) if payload.code != Some(0) => {
+                return Err(format!("whisper.cpp failed: {}", last_error));
+            }
+            _ => {}
+        }
+    }
+
+    let json = tokio::fs::read_to_string(&json_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let _ = tokio::fs::remove_file(&json_path).await;
+    parse_output(&json).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_output_works() {
+        let json = r#"{
+            "systeminfo": "AVX = 1",
+            "result": {"language": "en"},
+            "transcription": [
+                {
+                    "timestamps": {"from": "00:00:00,000", "to": "00:00:02,500"},
+                    "offsets": {"from": 0, "to": 2500},
+                    "text": " Hello there."
+                },
+                {
+                    "timestamps": {"from": "00:00:02,500", "to": "00:00:04,000"},
+                    "offsets": {"from": 2500, "to": 4000},
+                    "text": " How are you?"
+                }
+            ]
+        }"#;
+        let segments = parse_output(json).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].start, 2.5);
+        assert_eq!(segments[1].end, 4.0);
+        assert_eq!(segments[1].text, " How are you?");
+        assert!(segments[1].words.is_empty());
+    }
+
+    #[test]
+    fn merge_tokens_works() {
+        let json = r#"{
+            "transcription": [
+                {
+                    "offsets": {"from": 0, "to": 2000},
+                    "text": " Hello there.",
+                    "tokens": [
+                        {"text": "[_BEG_]", "offsets": {"from": 0, "to": 0}, "id": 50364, "p": 0.9},
+                        {"text": " Hel", "offsets": {"from": 0, "to": 300}, "id": 1, "p": 0.9},
+                        {"text": "lo", "offsets": {"from": 300, "to": 600}, "id": 2, "p": 0.9},
+                        {"text": " there", "offsets": {"from": 700, "to": 1200}, "id": 3, "p": 0.9},
+                        {"text": ".", "offsets": {"from": 1200, "to": 1300}, "id": 4, "p": 0.9},
+                        {"text": "[_TT_100]", "offsets": {"from": 2000, "to": 2000}, "id": 50464, "p": 0.9}
+                    ]
+                }
+            ]
+        }"#;
+        let segments = parse_output(json).unwrap();
+        assert_eq!(
+            segments[0].words,
+            vec![
+                Word {
+                    word: " Hello".to_string(),
+                    start: 0.0,
+                    end: 0.6
+                },
+                Word {
+                    word: " there.".to_string(),
+                    start: 0.7,
+                    end: 1.3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lines_works() {
+        assert_eq!(
+            parse_progress("whisper_print_progress_callback: progress =  45%\n"),
+            Some(45.0)
+        );
+        assert_eq!(
+            parse_progress("whisper_init_from_file: loading model"),
+            None
+        );
+        assert_eq!(
+            parse_segment_line("[00:00:01.000 --> 00:00:04.000]   some text\n"),
+            Some("   some text\n")
+        );
+        assert_eq!(parse_segment_line("[info] loading"), None);
+    }
+}
@@ -1,8 +1,16 @@
 import * as React from "react";
-import { Captions, FileText, XIcon } from "lucide-react";
+import {
+  Captions,
+  FileDown,
+  FileText,
+  Headphones,
+  Subtitles,
+  XIcon,
+} from "lucide-react";
 
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { downloadDir, join } from "@tauri-apps/api/path";
 
 import "./App.css";
 import CaptionCheckBox from "./components/CaptionCheckBox";
@@ -10,14 +18,34 @@ import VideoItems from "./components/VideoItems";
 import { useToast } from "hooks/ToastProvider";
 import SettingsModal from "components/SettingsModal";
 import StreamText from "components/StreamText";
+import TranscriptPlayer from "components/TranscriptPlayer";
 import { useVideoData } from "store/DataContext";
 import LanguageSelector from "components/LanguageSelector";
 import { formatDate } from "utils/files";
 
+// spoken languages to transcribe with, "auto" leaves it to the transcriber
+const SPOKEN_LANGUAGES = [
+  { code: "auto", name: "Auto" },
+  { code: "en", name: "English" },
+  { code: "zh", name: "中文" },
+  { code: "ja", name: "日本語" },
+  { code: "ko", name: "한국어" },
+  { code: "es", name: "Español" },
+  { code: "fr", name: "Français" },
+  { code: "de", name: "Deutsch" },
+];
+
+// payload of the `split_progress` events sent while long audio is cut up
+type SplitProgress = {
+  input_id: number;
+  percent: number;
+};
+
 function App() {
   const [url, setUrl] = React.useState<string>("");
 
   const [selectedLanguage, setSelectedLanguage] = React.useState<string>("en");
+  const [spokenLanguage, setSpokenLanguage] = React.useState<string>("auto");
   const [imgUrl, setImgUrl] = React.useState<string | null>(null);
 
   const {
@@ -32,15 +60,24 @@ function App() {
   const [content, setContent] = React.useState<string>("");
   const [summary, setSummary] = React.useState<string>("");
   const [auto, setAuto] = React.useState<boolean>(false);
+  // play the audio along the transcript instead of showing the text
+  const [listening, setListening] = React.useState<boolean>(false);
+  const [splitProgress, setSplitProgress] =
+    React.useState<SplitProgress | null>(null);
 
   const { addToast } = useToast();
 
   React.useEffect(() => {
     if (currentVideo !== null) {
       setImgUrl(null);
+      setListening(false);
       setContent(currentVideo.transcripts || "");
       setSummary(currentVideo.summary || "");
-      handle_new_video_image(currentVideo.thumbnail_url);
+      setSpokenLanguage(currentVideo.language || "auto");
+      // files imported from disk have no thumbnail
+      if (currentVideo.thumbnail_url) {
+        handle_new_video_image(currentVideo.thumbnail_url);
+      }
     } else {
       setContent("");
       setSummary("");
@@ -80,7 +117,11 @@ function App() {
         updateCurrentVideo(-1);
       }
       setInProgress(true);
-      await invoke("run_yt", { url: parse_url, input_id });
+      await invoke("run_yt", {
+        url: parse_url,
+        input_id,
+        language: spokenLanguage,
+      });
       fetchVideos();
     } catch (error) {
       const error_msg = error as string;
@@ -99,9 +140,15 @@ function App() {
       }
     } finally {
       setInProgress(false);
+      setSplitProgress(null);
     }
   }
 
+  async function handle_cancel_split() {
+    if (splitProgress === null) return;
+    await invoke("cancel_split", { input_id: splitProgress.input_id });
+  }
+
   async function handle_summary() {
     if (currentVideo === null || currentVideo.transcripts === null) return;
     try {
@@ -123,6 +170,48 @@ function App() {
     }
   }
 
+  // write a file named `file_name` into the downloads folder with `command`,
+  // there is no save dialog to pick another place
+  async function export_to_downloads(
+    command: string,
+    file_name: string,
+    args: Record<string, unknown>,
+  ) {
+    try {
+      const output_path = await join(await downloadDir(), file_name);
+      const path: string = await invoke(command, { ...args, output_path });
+      addToast({
+        message: `Saved to ${path}`,
+        variant: "success",
+        duration: 5000,
+      });
+    } catch (error) {
+      addToast({
+        message: error as string,
+        variant: "error",
+        duration: 5000,
+      });
+    }
+  }
+
+  // the summary as markdown, with chapter stills when the video was imported
+  // from disk
+  function handle_report() {
+    if (currentVideo === null || !currentVideo.summary) return;
+    export_to_downloads("export_report", `${currentVideo.video_id}-summary.md`, {
+      input_id: currentVideo.id,
+      video_path: null,
+    });
+  }
+
+  // the transcript as .srt, cues are timed from the words when they are known
+  function handle_subtitles() {
+    if (currentVideo === null || currentVideo.transcripts === null) return;
+    export_to_downloads("export_subtitles", `${currentVideo.video_id}.srt`, {
+      input_id: currentVideo.id,
+    });
+  }
+
   React.useEffect(() => {
     const unlisten = listen("stream", (event) => {
       if (event.payload === "[start]") {
@@ -162,10 +251,15 @@ function App() {
       }
     });
 
+    const unlisten_split = listen<SplitProgress>("split_progress", (event) => {
+      setSplitProgress(event.payload.percent < 100 ? event.payload : null);
+    });
+
     return () => {
       unlisten.then((fn) => fn());
       unlisten_summary.then((fn) => fn());
       unlisten_state.then((fn) => fn());
+      unlisten_split.then((fn) => fn());
     };
   }, [setInProgress, addToast, fetchVideos]);
 
@@ -217,8 +311,20 @@ function App() {
               id="url-input"
               className="p-2 rounded-md w-1/2 min-w-96"
               onChange={(e) => setUrl(e.currentTarget.value)}
-              placeholder="Enter a video url..."
+              placeholder="Enter a video url or a local file path..."
             />
+            <select
+              className="p-2 rounded-md"
+              aria-label="Spoken language"
+              value={spokenLanguage}
+              onChange={(e) => setSpokenLanguage(e.currentTarget.value)}
+            >
+              {SPOKEN_LANGUAGES.map((language) => (
+                <option key={language.code} value={language.code}>
+                  {language.name}
+                </option>
+              ))}
+            </select>
             <button
               type="button"
               className="flex items-center space-x-2 px-4 py-2
@@ -230,28 +336,69 @@ function App() {
               <Captions className="w-7 h-7" />
               <span>Transcript</span>
             </button>
+            <button
+              type="button"
+              className="flex items-center space-x-2 px-4 py-2
+                          bg-purple-500 text-white rounded-lg
+                          hover:bg-purple-600 active:bg-purple-700 disabled:bg-purple-300 disabled:text-gray-500 disabled:cursor-default"
+              onClick={handle_subtitles}
+              disabled={inProgress || !currentVideo?.transcripts}
+            >
+              <Subtitles className="w-7 h-7" />
+              <span>Subtitles</span>
+            </button>
+            <button
+              type="button"
+              className="flex items-center space-x-2 px-4 py-2
+                          bg-purple-500 text-white rounded-lg
+                          hover:bg-purple-600 active:bg-purple-700 disabled:bg-purple-300 disabled:text-gray-500 disabled:cursor-default"
+              onClick={() => setListening(!listening)}
+              disabled={inProgress || !currentVideo?.transcripts}
+            >
+              <Headphones className="w-7 h-7" />
+              <span>{listening ? "Text" : "Listen"}</span>
+            </button>
+            {splitProgress && (
+              <div className="flex items-center space-x-2 text-white">
+                <span>Splitting {Math.round(splitProgress.percent)}%</span>
+                <button
+                  type="button"
+                  className="p-2 rounded-full transition-colors focus:outline-none"
+                  aria-label="Cancel split"
+                  onClick={handle_cancel_split}
+                >
+                  <XIcon className="w-6 h-6 text-gray-300 hover:text-gray-200 active:text-gray-100" />
+                </button>
+              </div>
+            )}
           </div>
 
           <div className="flex flex-row justify-between items-stretch w-full overflow-hidden h-full">
             <div className="w-1/2 overflow-y-auto h-full">
-              {currentVideo && (
+              {listening && currentVideo ? (
+                <TranscriptPlayer key={currentVideo.id} inputId={currentVideo.id} />
+              ) : (
                 <>
-                  <h2 className="text-center text-xl text-gray-700">
-                    {currentVideo.title}
-                  </h2>
-                  <p className="text-right text-sm pr-2 text-gray-700">
-                    {formatDate(currentVideo.upload_date)}
-                  </p>
-                  {imgUrl && (
-                    <img
-                      src={imgUrl}
-                      className="mx-auto w-70 h-40 rounded-lg"
-                      alt="thumbnail"
-                    />
+                  {currentVideo && (
+                    <>
+                      <h2 className="text-center text-xl text-gray-700">
+                        {currentVideo.title}
+                      </h2>
+                      <p className="text-right text-sm pr-2 text-gray-700">
+                        {formatDate(currentVideo.upload_date)}
+                      </p>
+                      {imgUrl && (
+                        <img
+                          src={imgUrl}
+                          className="mx-auto w-70 h-40 rounded-lg"
+                          alt="thumbnail"
+                        />
+                      )}
+                    </>
                   )}
+                  <StreamText content={content} />
                 </>
               )}
-              <StreamText content={content} />
             </div>
             <div className="flex flex-col w-1/2 h-full">
               <div className="flex bg-zinc-600 py-2 justify-center items-center gap-7">
@@ -271,6 +418,17 @@ function App() {
                   <FileText className="w-7 h-7" />
                   <span>Summary</span>
                 </button>
+                <button
+                  type="button"
+                  onClick={handle_report}
+                  className="flex items-center space-x-2 px-4 py-2
+                              bg-green-500 text-white rounded-lg
+                              hover:bg-green-600 active:bg-green-700 disabled:bg-green-300 disabled:text-gray-500 disabled:cursor-default"
+                  disabled={inProgress || !summary}
+                >
+                  <FileDown className="w-7 h-7" />
+                  <span>Report</span>
+                </button>
               </div>
               <div className="flex-1 overflow-y-auto bg-zinc-300 h-full">
                 <StreamText content={summary} />
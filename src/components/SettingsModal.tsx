@@ -6,6 +6,24 @@ import { useSettings } from "store/SettingsProvider";
 import type { SettingsType } from "types/settings";
 import { useVideoData } from "store/DataContext";
 
+// the values `transcriber::from_settings` accepts, empty picks local when a
+// model path is set and openai otherwise
+const TRANSCRIBERS = [
+  { value: "", name: "Default" },
+  { value: "openai", name: "OpenAI compatible" },
+  { value: "groq", name: "Groq" },
+  { value: "local", name: "Local whisper.cpp" },
+  { value: "asr", name: "ASR server" },
+];
+
+// the names `SpeechCodec::from_name` accepts, empty is opus
+const UPLOAD_FORMATS = [
+  { value: "", name: "Default (opus)" },
+  { value: "opus", name: "Opus" },
+  { value: "flac", name: "FLAC" },
+  { value: "mp3", name: "MP3" },
+];
+
 const SettingsModal: React.FC = () => {
   const [isOpen, setIsOpen] = React.useState(false);
   const [showApiKey, setShowApiKey] = React.useState(false);
@@ -16,7 +34,9 @@ const SettingsModal: React.FC = () => {
 
   const { deleteAll } = useVideoData();
 
-  const handleInputChange = (e: React.ChangeEvent<HTMLInputElement>) => {
+  const handleInputChange = (
+    e: React.ChangeEvent<HTMLInputElement | HTMLSelectElement>,
+  ) => {
     const { name, value } = e.target;
     setSettings({ ...settings, [name]: value });
   };
@@ -201,6 +221,29 @@ const SettingsModal: React.FC = () => {
                 />
               </div>
 
+              <div>
+                <label
+                  htmlFor="transcriber"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  Transcriber
+                </label>
+                <select
+                  id="transcriber"
+                  name="transcriber"
+                  value={settings.transcriber?.toLowerCase() || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                >
+                  {TRANSCRIBERS.map((transcriber) => (
+                    <option key={transcriber.value} value={transcriber.value}>
+                      {transcriber.name}
+                    </option>
+                  ))}
+                </select>
+              </div>
+
               <div>
                 <label
                   htmlFor="whisperModelName"
@@ -220,6 +263,25 @@ const SettingsModal: React.FC = () => {
                 />
               </div>
 
+              <div>
+                <label
+                  htmlFor="whisperLocalModel"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  Local Whisper Model (GGML path, offline)
+                </label>
+                <input
+                  type="text"
+                  id="whisperLocalModel"
+                  name="whisperLocalModel"
+                  value={settings.whisperLocalModel || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  placeholder="e.g. /path/to/ggml-base.en.bin"
+                />
+              </div>
+
               <div>
                 <label
                   htmlFor="proxy"
@@ -238,6 +300,181 @@ const SettingsModal: React.FC = () => {
                   placeholder="Enter AI supplier URL"
                 />
               </div>
+
+              <div>
+                <label
+                  htmlFor="uploadFormat"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  Upload Format
+                </label>
+                <select
+                  id="uploadFormat"
+                  name="uploadFormat"
+                  value={settings.uploadFormat?.toLowerCase() || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                >
+                  {UPLOAD_FORMATS.map((format) => (
+                    <option key={format.value} value={format.value}>
+                      {format.name}
+                    </option>
+                  ))}
+                </select>
+              </div>
+
+              <div>
+                <label
+                  htmlFor="uploadBitrate"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  Upload Bitrate (kbps)
+                </label>
+                <input
+                  type="text"
+                  id="uploadBitrate"
+                  name="uploadBitrate"
+                  value={settings.uploadBitrate || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  placeholder="32"
+                />
+              </div>
+
+              <div className="flex items-center space-x-2">
+                <input
+                  type="checkbox"
+                  id="speechOnly"
+                  name="speechOnly"
+                  checked={settings.speechOnly || false}
+                  onChange={(e) =>
+                    setSettings({ ...settings, speechOnly: e.target.checked })
+                  }
+                  className="h-4 w-4 border-gray-300 rounded focus:ring-2 focus:ring-blue-500"
+                />
+                <label
+                  htmlFor="speechOnly"
+                  className="text-sm font-medium text-gray-700"
+                >
+                  Transcribe speech only (skip silence and music)
+                </label>
+              </div>
+
+              <div className="flex items-center space-x-2">
+                <input
+                  type="checkbox"
+                  id="downmix"
+                  name="downmix"
+                  checked={settings.downmix || false}
+                  onChange={(e) =>
+                    setSettings({ ...settings, downmix: e.target.checked })
+                  }
+                  className="h-4 w-4 border-gray-300 rounded focus:ring-2 focus:ring-blue-500"
+                />
+                <label
+                  htmlFor="downmix"
+                  className="text-sm font-medium text-gray-700"
+                >
+                  Downmix to mono before filtering
+                </label>
+              </div>
+
+              <div className="flex items-center space-x-2">
+                <input
+                  type="checkbox"
+                  id="denoise"
+                  name="denoise"
+                  checked={settings.denoise || false}
+                  onChange={(e) =>
+                    setSettings({ ...settings, denoise: e.target.checked })
+                  }
+                  className="h-4 w-4 border-gray-300 rounded focus:ring-2 focus:ring-blue-500"
+                />
+                <label
+                  htmlFor="denoise"
+                  className="text-sm font-medium text-gray-700"
+                >
+                  Reduce background noise
+                </label>
+              </div>
+
+              <div className="flex items-center space-x-2">
+                <input
+                  type="checkbox"
+                  id="loudnorm"
+                  name="loudnorm"
+                  checked={settings.loudnorm || false}
+                  onChange={(e) =>
+                    setSettings({ ...settings, loudnorm: e.target.checked })
+                  }
+                  className="h-4 w-4 border-gray-300 rounded focus:ring-2 focus:ring-blue-500"
+                />
+                <label
+                  htmlFor="loudnorm"
+                  className="text-sm font-medium text-gray-700"
+                >
+                  Normalize loudness
+                </label>
+              </div>
+
+              <div>
+                <label
+                  htmlFor="highpass"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  High-pass Filter (Hz)
+                </label>
+                <input
+                  type="text"
+                  id="highpass"
+                  name="highpass"
+                  value={settings.highpass || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  placeholder="80"
+                />
+              </div>
+
+              <div>
+                <label
+                  htmlFor="speedFactor"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  Upload Speed-up (1.0 - 3.0)
+                </label>
+                <input
+                  type="text"
+                  id="speedFactor"
+                  name="speedFactor"
+                  value={settings.speedFactor || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  placeholder="1.5"
+                />
+              </div>
+
+              <div>
+                <label
+                  htmlFor="transcribeConcurrency"
+                  className="block text-sm font-medium text-gray-700 mb-1"
+                >
+                  Parallel Transcriptions (1 - 8)
+                </label>
+                <input
+                  type="text"
+                  id="transcribeConcurrency"
+                  name="transcribeConcurrency"
+                  value={settings.transcribeConcurrency || ""}
+                  onChange={handleInputChange}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm 
+                             focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  placeholder="3"
+                />
+              </div>
             </div>
           </div>
 
@@ -0,0 +1,129 @@
+import * as React from "react";
+import { invoke } from "@tauri-apps/api/core";
+import type { Segment, Word } from "types/db";
+
+interface TranscriptPlayerProps {
+  inputId: number;
+}
+
+// the words of a segment, or the whole segment as one word when the
+// transcriber gave no word timings
+function segmentWords(segment: Segment): Word[] {
+  if (segment.words && segment.words.length > 0) return segment.words;
+  return [{ word: segment.text, start: segment.start, end: segment.end }];
+}
+
+// the openai and groq apis send bare words, local whisper a leading space, and
+// scripts written without spaces need none
+const UNSPACED =
+  /^[\s\u0e00-\u0eff\u3000-\u30ff\u3400-\u4dbf\u4e00-\u9fff\uf900-\ufaff\uff00-\uffef]/;
+
+function isActive(word: Word, time: number): boolean {
+  return word.start <= time && time < word.end;
+}
+
+const TranscriptPlayer: React.FC<TranscriptPlayerProps> = ({ inputId }) => {
+  const audioRef = React.useRef<HTMLAudioElement>(null);
+  const activeRef = React.useRef<HTMLSpanElement>(null);
+  const [audioUrl, setAudioUrl] = React.useState<string | null>(null);
+  const [segments, setSegments] = React.useState<Segment[]>([]);
+  const [time, setTime] = React.useState<number>(0);
+  const [error, setError] = React.useState<string | null>(null);
+
+  React.useEffect(() => {
+    let url: string | null = null;
+    setAudioUrl(null);
+    setError(null);
+    Promise.all([
+      invoke<Segment[]>("get_segments", { input_id: inputId }),
+      invoke<ArrayBuffer>("load_audio", { input_id: inputId }),
+    ])
+      .then(([segments, bytes]) => {
+        url = URL.createObjectURL(new Blob([bytes]));
+        setSegments(segments);
+        setAudioUrl(url);
+      })
+      .catch((error) => setError(error as string));
+    return () => {
+      if (url !== null) URL.revokeObjectURL(url);
+    };
+  }, [inputId]);
+
+  // timeupdate only fires a few times a second, follow the playhead per frame
+  // while playing so the highlight keeps up with every word
+  React.useEffect(() => {
+    const audio = audioRef.current;
+    if (audio === null) return;
+    let frame = 0;
+    const follow = () => {
+      setTime(audio.currentTime);
+      if (!audio.paused) frame = requestAnimationFrame(follow);
+    };
+    const onPlay = () => {
+      frame = requestAnimationFrame(follow);
+    };
+    const onSeeked = () => setTime(audio.currentTime);
+    audio.addEventListener("play", onPlay);
+    audio.addEventListener("seeked", onSeeked);
+    return () => {
+      cancelAnimationFrame(frame);
+      audio.removeEventListener("play", onPlay);
+      audio.removeEventListener("seeked", onSeeked);
+    };
+  }, [audioUrl]);
+
+  React.useEffect(() => {
+    activeRef.current?.scrollIntoView({ block: "nearest" });
+  });
+
+  function seek(start: number) {
+    const audio = audioRef.current;
+    if (audio === null) return;
+    audio.currentTime = start;
+    setTime(start);
+    audio.play();
+  }
+
+  if (error !== null) {
+    return <p className="p-5 text-red-600">{error}</p>;
+  }
+  if (audioUrl === null) {
+    return <p className="p-5 text-gray-700">Loading audio...</p>;
+  }
+
+  return (
+    <div className="flex flex-col h-full">
+      <audio ref={audioRef} src={audioUrl} controls className="w-full p-2" />
+      <div className="flex-1 overflow-y-auto p-5 space-y-2 text-gray-700 leading-relaxed">
+        {segments.map((segment, segmentIndex) => (
+          <p
+            key={segmentIndex}
+            className={
+              isActive(segment, time) ? "bg-gray-300 rounded" : undefined
+            }
+          >
+            {segmentWords(segment).map((word, index) => {
+              const active = isActive(word, time);
+              return (
+                <React.Fragment key={index}>
+                  {index > 0 && !UNSPACED.test(word.word) && " "}
+                  <span
+                    ref={active ? activeRef : undefined}
+                    className={`cursor-pointer rounded hover:bg-purple-200 ${
+                      active ? "bg-yellow-300" : ""
+                    }`}
+                    onClick={() => seek(word.start)}
+                  >
+                    {word.word}
+                  </span>
+                </React.Fragment>
+              );
+            })}
+          </p>
+        ))}
+      </div>
+    </div>
+  );
+};
+
+export default TranscriptPlayer;
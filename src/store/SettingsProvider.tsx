@@ -7,10 +7,21 @@ const defaultSettings: SettingsType = {
   apiKey: null,
   aiUrl: null,
   aiModelName: null,
+  transcriber: null,
   whisperApiKey: null,
   whisperUrl: null,
   whisperModelName: null,
+  whisperLocalModel: null,
   proxy: null,
+  uploadFormat: null,
+  uploadBitrate: null,
+  speechOnly: null,
+  downmix: null,
+  highpass: null,
+  denoise: null,
+  loudnorm: null,
+  speedFactor: null,
+  transcribeConcurrency: null,
 };
 
 interface SettingsContextType {
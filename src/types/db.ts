@@ -9,6 +9,7 @@ export interface VideoData {
   keywords: string;
   timestamp: number;
   thumbnail_url: string;
+  language: string | null;
 }
 
 export interface VideoItemProps {
@@ -18,3 +19,18 @@ export interface VideoItemProps {
 export interface VideoListProps {
   items: VideoData[];
 }
+
+// what `transcripts` holds as json, words are only there when the
+// transcriber gave word timings
+export interface Word {
+  word: string;
+  start: number;
+  end: number;
+}
+
+export interface Segment {
+  start: number;
+  end: number;
+  text: string;
+  words?: Word[];
+}
@@ -2,8 +2,19 @@ export interface SettingsType {
   apiKey: string | null;
   aiUrl: string | null;
   aiModelName: string | null;
+  transcriber: string | null;
   whisperApiKey: string | null;
   whisperUrl: string | null;
   whisperModelName: string | null;
+  whisperLocalModel: string | null;
   proxy: string | null;
+  uploadFormat: string | null;
+  uploadBitrate: string | null;
+  speechOnly: boolean | null;
+  downmix: boolean | null;
+  highpass: string | null;
+  denoise: boolean | null;
+  loudnorm: boolean | null;
+  speedFactor: string | null;
+  transcribeConcurrency: string | null;
 }